@@ -1,4 +1,4 @@
-import { PublicKey, TransactionInstruction } from "@solana/web3.js";
+import { AccountMeta, PublicKey, TransactionInstruction } from "@solana/web3.js";
 import * as BufferLayout from "buffer-layout";
 
 export interface PoolInfo {
@@ -51,82 +51,215 @@ export const PoolLayout: typeof BufferLayout.Structure = BufferLayout.struct(
   ]
 );
 
+export const MINIMUM_RESERVE_SEED = "minimum_reserve";
+export const LOCKER_SEED = "locker";
+
+// Per-token accounts of InitializePool, in the order the program reads them
+export interface InitPoolToken {
+  srcAccount: PublicKey;
+  mintAccount: PublicKey;
+  treasuryAccount: PublicKey;
+  vaultAccount: PublicKey; // fresh keypair, signs the transaction
+  minimumAccount: PublicKey; // derived from the mint, may not exist
+}
+
+// Per-token accounts of the deposits and withdrawals
+export interface LiquidityToken {
+  account: PublicKey; // source on deposits, destination on withdrawals
+  mintAccount: PublicKey;
+  treasuryAccount: PublicKey;
+}
+
+// Optional accounts of Swap, the program id stands in for the missing ones
+export interface SwapOptionalAccounts {
+  allowlistAccount?: PublicKey;
+  referrerAccount?: PublicKey; // token account of the ask mint
+  volumeAccount?: PublicKey;
+  discountAccount?: PublicKey; // token account of the discount mint
+  instructionsAccount?: PublicKey;
+  registryAccount?: PublicKey;
+  priceAccount?: PublicKey;
+}
+
+export interface Tier {
+  threshold: number; // volume for fee tiers, balance for holder tiers
+  discount: number;
+}
+
+// Per-token accounts of ClosePool
+export interface ClosePoolToken {
+  treasuryAccount: PublicKey;
+  vaultAccount: PublicKey;
+  mintAccount: PublicKey;
+  lockTokenAccount: PublicKey; // associated account of the locker, receives the treasury
+  dstTokenAccount: PublicKey; // receives the vault
+}
+
+export const findMinimumReserveAddress = async (
+  mintAccount: PublicKey,
+  poolProgramId: PublicKey
+): Promise<PublicKey> => {
+  const [minimumAccount] = await PublicKey.findProgramAddress(
+    [mintAccount.toBuffer(), Buffer.from(MINIMUM_RESERVE_SEED)],
+    poolProgramId
+  );
+  return minimumAccount;
+};
+
+export const findLockerAddress = async (
+  poolAccount: PublicKey,
+  poolProgramId: PublicKey
+): Promise<PublicKey> => {
+  const [locker] = await PublicKey.findProgramAddress(
+    [poolAccount.toBuffer(), Buffer.from(LOCKER_SEED)],
+    poolProgramId
+  );
+  return locker;
+};
+
+const encodeData = (
+  layout: typeof BufferLayout.Structure,
+  fields: Object
+): Buffer => {
+  let data = Buffer.alloc(1024);
+  const encodeLength = layout.encode(fields, data);
+  return data.slice(0, encodeLength);
+};
+
+const tagData = (instruction: number): Buffer =>
+  encodeData(BufferLayout.struct([BufferLayout.u8("instruction")]), {
+    instruction,
+  });
+
+const u8Data = (instruction: number, value: number): Buffer =>
+  encodeData(
+    BufferLayout.struct([BufferLayout.u8("instruction"), BufferLayout.u8("value")]),
+    { instruction, value }
+  );
+
+const u64Data = (instruction: number, value: number): Buffer =>
+  encodeData(
+    BufferLayout.struct([BufferLayout.u8("instruction"), BufferLayout.nu64("value")]),
+    { instruction, value }
+  );
+
+const tiersData = (instruction: number, tiers: Tier[]): Buffer =>
+  encodeData(
+    BufferLayout.struct([
+      BufferLayout.u8("instruction"),
+      BufferLayout.seq(
+        BufferLayout.struct([
+          BufferLayout.nu64("threshold"),
+          BufferLayout.nu64("discount"),
+        ]),
+        tiers.length,
+        "tiers"
+      ),
+    ]),
+    { instruction, tiers }
+  );
+
+// flatMap is not in the ES6 lib
+const flatKeys = <T>(items: T[], keys: (item: T) => AccountMeta[]): AccountMeta[] =>
+  ([] as AccountMeta[]).concat(...items.map(keys));
+
+const amountsLayout = (count: number, property: string) =>
+  BufferLayout.seq(BufferLayout.nu64(), count, property);
+
+const liquidityTokenKeys = (tokens: LiquidityToken[]) =>
+  flatKeys(tokens, (token) => [
+    { pubkey: token.account, isSigner: false, isWritable: true },
+    { pubkey: token.mintAccount, isSigner: false, isWritable: false },
+    { pubkey: token.treasuryAccount, isSigner: false, isWritable: true },
+  ]);
+
+// The temporary wSOL account is only passed when a native wallet takes part
+const nativeKeys = (wsolAccount?: PublicKey): AccountMeta[] =>
+  wsolAccount ? [{ pubkey: wsolAccount, isSigner: true, isWritable: true }] : [];
+
+// The pool owner signs, unless it is a multisig and its members sign instead
+const ownerKeys = (
+  ownerAccount: PublicKey,
+  poolAccount: PublicKey,
+  signers: PublicKey[]
+) => ({
+  head: [
+    { pubkey: ownerAccount, isSigner: signers.length === 0, isWritable: false },
+    { pubkey: poolAccount, isSigner: false, isWritable: true },
+  ],
+  tail: signers.map((pubkey) => ({ pubkey, isSigner: true, isWritable: false })),
+});
+
+// A pool-owner instruction without token accounts, e.g. FreezePool
+const ownerInstruction = (
+  ownerAccount: PublicKey,
+  poolAccount: PublicKey,
+  signers: PublicKey[],
+  poolProgramId: PublicKey,
+  data: Buffer,
+  accounts: PublicKey[] = []
+): TransactionInstruction => {
+  const { head, tail } = ownerKeys(ownerAccount, poolAccount, signers);
+  const keys = [
+    ...head,
+    ...accounts.map((pubkey) => ({ pubkey, isSigner: false, isWritable: false })),
+    ...tail,
+  ];
+  return new TransactionInstruction({ keys, programId: poolProgramId, data });
+};
+
 export const createInitPoolInstruction = (
   payerAccount: PublicKey,
   ownerAccount: PublicKey,
   poolAccount: PublicKey,
   lptAccount: PublicKey,
   mintLptAccount: PublicKey,
-  vaultAccount: PublicKey,
   proofAccount: PublicKey,
-  srcSAccount: PublicKey,
-  mintSAccount: PublicKey,
-  treasurySAccount: PublicKey,
-  srcAAccount: PublicKey,
-  mintAAccount: PublicKey,
-  treasuryAAccount: PublicKey,
-  srcBAccount: PublicKey,
-  mintBAccount: PublicKey,
-  treasuryBAccount: PublicKey,
+  lockAccount: PublicKey,
+  lockerAccount: PublicKey,
   treasurerAccount: PublicKey,
   sysProgramId: PublicKey,
   spltProgramId: PublicKey,
+  splt2022ProgramId: PublicKey,
   sysVarRentAccount: PublicKey,
   splAtaProgramId: PublicKey,
+  tokens: InitPoolToken[],
+  reserves: number[],
   poolProgramId: PublicKey,
-  amountS: number,
-  amountA: number,
-  amountB: number,
 ): TransactionInstruction => {
   const keys = [
-    { pubkey: payerAccount, isSigner: false, isWritable: true },
+    { pubkey: payerAccount, isSigner: true, isWritable: true },
     { pubkey: ownerAccount, isSigner: false, isWritable: false },
-    { pubkey: poolAccount, isSigner: false, isWritable: false },
-    { pubkey: lptAccount, isSigner: false, isWritable: false },
+    { pubkey: poolAccount, isSigner: true, isWritable: true },
+    { pubkey: lptAccount, isSigner: false, isWritable: true },
     { pubkey: mintLptAccount, isSigner: false, isWritable: true },
-    { pubkey: vaultAccount, isSigner: false, isWritable: false },
-    { pubkey: proofAccount, isSigner: false, isWritable: true },
-    { pubkey: srcSAccount, isSigner: false, isWritable: false },
-    { pubkey: mintSAccount, isSigner: false, isWritable: false },
-    { pubkey: treasurySAccount, isSigner: false, isWritable: false },
-    { pubkey: srcAAccount, isSigner: false, isWritable: false },
-    { pubkey: mintAAccount, isSigner: false, isWritable: false },
-    { pubkey: treasuryAAccount, isSigner: false, isWritable: false },
-    { pubkey: srcBAccount, isSigner: false, isWritable: false },
-    { pubkey: mintBAccount, isSigner: false, isWritable: false },
-    { pubkey: treasuryBAccount, isSigner: false, isWritable: false },
+    { pubkey: proofAccount, isSigner: false, isWritable: false },
+    { pubkey: lockAccount, isSigner: false, isWritable: true },
+    { pubkey: lockerAccount, isSigner: false, isWritable: false },
     { pubkey: treasurerAccount, isSigner: false, isWritable: false },
     { pubkey: sysProgramId, isSigner: false, isWritable: false },
     { pubkey: spltProgramId, isSigner: false, isWritable: false },
+    { pubkey: splt2022ProgramId, isSigner: false, isWritable: false },
     { pubkey: sysVarRentAccount, isSigner: false, isWritable: false },
     { pubkey: splAtaProgramId, isSigner: false, isWritable: false },
+    ...flatKeys(tokens, (token) => [
+      { pubkey: token.srcAccount, isSigner: false, isWritable: true },
+      { pubkey: token.mintAccount, isSigner: false, isWritable: false },
+      { pubkey: token.treasuryAccount, isSigner: false, isWritable: true },
+      { pubkey: token.vaultAccount, isSigner: true, isWritable: true },
+      { pubkey: token.minimumAccount, isSigner: false, isWritable: false },
+    ]),
   ];
 
   const commandDataLayout = BufferLayout.struct([
-    BufferLayout.u8("instruction"),    
-    BufferLayout.nu64("reserve_s"),
-    BufferLayout.nu64("reserve_a"),
-    BufferLayout.nu64("reserve_b"),    
+    BufferLayout.u8("instruction"),
+    amountsLayout(reserves.length, "reserves"),
   ]);
 
-  let data = Buffer.alloc(1024);
-  {
-    const encodeLength = commandDataLayout.encode(
-      {
-        instruction: 0,
-        reserve_s: amountS,
-        reserve_a: amountA,
-        reserve_b: amountB,
-      },
-      data
-    );
-    data = data.slice(0, encodeLength);
-  }
-
   return new TransactionInstruction({
     keys,
     programId: poolProgramId,
-    data,
+    data: encodeData(commandDataLayout, { instruction: 0, reserves }),
   });
 }
 
@@ -135,56 +268,44 @@ export const addLPInstruction = (
   poolAccount: PublicKey,
   lptAccount: PublicKey,
   mintLptAccount: PublicKey,
-  srcSAccount: PublicKey,
-  treasurySAccount: PublicKey,
-  srcAAccount: PublicKey,
-  treasuryAAccount: PublicKey,
-  srcBAccount: PublicKey,
-  treasuryBAccount: PublicKey,
   treasurerAccount: PublicKey,
   spltProgramId: PublicKey,
+  splt2022ProgramId: PublicKey,
+  sysProgramId: PublicKey,
+  tokens: LiquidityToken[],
+  deltas: number[],
+  minLpt: number,
   poolProgramId: PublicKey,
+  wsolAccount?: PublicKey,
 ): TransactionInstruction => {
   const keys = [
-    { pubkey: ownerAccount, isSigner: false, isWritable: false },
-    { pubkey: poolAccount, isSigner: false, isWritable: false },
-    { pubkey: lptAccount, isSigner: false, isWritable: false },
+    { pubkey: ownerAccount, isSigner: true, isWritable: true },
+    { pubkey: poolAccount, isSigner: false, isWritable: true },
+    { pubkey: lptAccount, isSigner: false, isWritable: true },
     { pubkey: mintLptAccount, isSigner: false, isWritable: true },
-    { pubkey: srcSAccount, isSigner: false, isWritable: false },
-    { pubkey: treasurySAccount, isSigner: false, isWritable: false },
-    { pubkey: srcAAccount, isSigner: false, isWritable: false },
-    { pubkey: treasuryAAccount, isSigner: false, isWritable: false },
-    { pubkey: srcBAccount, isSigner: false, isWritable: false },
-    { pubkey: treasuryBAccount, isSigner: false, isWritable: false },
     { pubkey: treasurerAccount, isSigner: false, isWritable: false },
     { pubkey: spltProgramId, isSigner: false, isWritable: false },
+    { pubkey: splt2022ProgramId, isSigner: false, isWritable: false },
+    { pubkey: sysProgramId, isSigner: false, isWritable: false },
+    ...liquidityTokenKeys(tokens),
+    ...nativeKeys(wsolAccount),
   ];
 
+  // The LPT bound comes first, the deltas fill the rest
   const commandDataLayout = BufferLayout.struct([
-    BufferLayout.u8("instruction"),    
-    BufferLayout.nu64("delta_s"),
-    BufferLayout.nu64("delta_a"),
-    BufferLayout.nu64("delta_b"),    
+    BufferLayout.u8("instruction"),
+    BufferLayout.nu64("min_lpt"),
+    amountsLayout(deltas.length, "deltas"),
   ]);
 
-  let data = Buffer.alloc(1024);
-  {
-    const encodeLength = commandDataLayout.encode(
-      {
-        instruction: 1,
-        delta_s: 5,
-        delta_a: 5,
-        delta_b: 5,        
-      },
-      data
-    );
-    data = data.slice(0, encodeLength);
-  }
-
   return new TransactionInstruction({
     keys,
     programId: poolProgramId,
-    data,
+    data: encodeData(commandDataLayout, {
+      instruction: 1,
+      min_lpt: minLpt,
+      deltas,
+    }),
   });
 }
 
@@ -193,51 +314,953 @@ export const removeLPInstruction = (
   poolAccount: PublicKey,
   lptAccount: PublicKey,
   mintLptAccount: PublicKey,
-  srcSAccount: PublicKey,
-  treasurySAccount: PublicKey,
-  srcAAccount: PublicKey,
-  treasuryAAccount: PublicKey,
-  srcBAccount: PublicKey,
-  treasuryBAccount: PublicKey,
   treasurerAccount: PublicKey,
   spltProgramId: PublicKey,
+  splt2022ProgramId: PublicKey,
+  sysProgramId: PublicKey,
+  splAtaProgramId: PublicKey,
+  tokens: LiquidityToken[],
+  lpt: number,
+  minAmounts: number[],
   poolProgramId: PublicKey,
+  wsolAccount?: PublicKey,
 ): TransactionInstruction => {
   const keys = [
-    { pubkey: ownerAccount, isSigner: false, isWritable: false },
-    { pubkey: poolAccount, isSigner: false, isWritable: false },
-    { pubkey: lptAccount, isSigner: false, isWritable: false },
+    { pubkey: ownerAccount, isSigner: true, isWritable: true },
+    { pubkey: poolAccount, isSigner: false, isWritable: true },
+    { pubkey: lptAccount, isSigner: false, isWritable: true },
     { pubkey: mintLptAccount, isSigner: false, isWritable: true },
-    { pubkey: srcSAccount, isSigner: false, isWritable: false },
-    { pubkey: treasurySAccount, isSigner: false, isWritable: false },
-    { pubkey: srcAAccount, isSigner: false, isWritable: false },
-    { pubkey: treasuryAAccount, isSigner: false, isWritable: false },
-    { pubkey: srcBAccount, isSigner: false, isWritable: false },
-    { pubkey: treasuryBAccount, isSigner: false, isWritable: false },
     { pubkey: treasurerAccount, isSigner: false, isWritable: false },
     { pubkey: spltProgramId, isSigner: false, isWritable: false },
+    { pubkey: splt2022ProgramId, isSigner: false, isWritable: false },
+    { pubkey: sysProgramId, isSigner: false, isWritable: false },
+    { pubkey: splAtaProgramId, isSigner: false, isWritable: false },
+    ...liquidityTokenKeys(tokens),
+    ...nativeKeys(wsolAccount),
   ];
 
   const commandDataLayout = BufferLayout.struct([
-    BufferLayout.u8("instruction"),    
-    BufferLayout.nu64("lpt"),    
+    BufferLayout.u8("instruction"),
+    BufferLayout.nu64("lpt"),
+    amountsLayout(minAmounts.length, "min_amounts"),
   ]);
 
-  let data = Buffer.alloc(1024);
-  {
-    const encodeLength = commandDataLayout.encode(
-      {
-        instruction: 2,
-        lpt: 8,        
-      },
-      data
-    );
-    data = data.slice(0, encodeLength);
+  return new TransactionInstruction({
+    keys,
+    programId: poolProgramId,
+    data: encodeData(commandDataLayout, {
+      instruction: 2,
+      lpt,
+      min_amounts: minAmounts,
+    }),
+  });
+}
+
+export const swapInstruction = (
+  payerAccount: PublicKey,
+  poolAccount: PublicKey,
+  vaultAccount: PublicKey, // vault of the ask token
+  srcAccount: PublicKey,
+  mintBidAccount: PublicKey,
+  treasuryBidAccount: PublicKey,
+  dstAccount: PublicKey,
+  mintAskAccount: PublicKey,
+  treasuryAskAccount: PublicKey,
+  treasurerAccount: PublicKey,
+  spltProgramId: PublicKey,
+  splt2022ProgramId: PublicKey,
+  sysProgramId: PublicKey,
+  splAtaProgramId: PublicKey,
+  amount: number,
+  limit: number,
+  poolProgramId: PublicKey,
+  optionalAccounts: SwapOptionalAccounts = {},
+  wsolAccount?: PublicKey,
+): TransactionInstruction => {
+  // The pool and the treasuries must stay at the indices the anti-sandwich check reads
+  const keys: AccountMeta[] = [
+    { pubkey: payerAccount, isSigner: true, isWritable: true },
+    { pubkey: poolAccount, isSigner: false, isWritable: true },
+    { pubkey: vaultAccount, isSigner: false, isWritable: true },
+    { pubkey: srcAccount, isSigner: false, isWritable: true },
+    { pubkey: mintBidAccount, isSigner: false, isWritable: false },
+    { pubkey: treasuryBidAccount, isSigner: false, isWritable: true },
+    { pubkey: dstAccount, isSigner: false, isWritable: true },
+    { pubkey: mintAskAccount, isSigner: false, isWritable: false },
+    { pubkey: treasuryAskAccount, isSigner: false, isWritable: true },
+    { pubkey: treasurerAccount, isSigner: false, isWritable: false },
+    { pubkey: spltProgramId, isSigner: false, isWritable: false },
+    { pubkey: splt2022ProgramId, isSigner: false, isWritable: false },
+    { pubkey: sysProgramId, isSigner: false, isWritable: false },
+    { pubkey: splAtaProgramId, isSigner: false, isWritable: false },
+    ...nativeKeys(wsolAccount),
+  ];
+
+  const optionalKeys = [
+    { pubkey: optionalAccounts.allowlistAccount, isWritable: false },
+    { pubkey: optionalAccounts.referrerAccount, isWritable: true },
+    { pubkey: optionalAccounts.volumeAccount, isWritable: true },
+    { pubkey: optionalAccounts.discountAccount, isWritable: false },
+    { pubkey: optionalAccounts.instructionsAccount, isWritable: false },
+    { pubkey: optionalAccounts.registryAccount, isWritable: false },
+    { pubkey: optionalAccounts.priceAccount, isWritable: false },
+  ];
+  // Trailing missing accounts are left out, the ones in between take the program id
+  let optionalCount = optionalKeys.length;
+  while (optionalCount > 0 && !optionalKeys[optionalCount - 1].pubkey) {
+    optionalCount--;
   }
+  optionalKeys.slice(0, optionalCount).forEach(({ pubkey, isWritable }) =>
+    keys.push({
+      pubkey: pubkey || poolProgramId,
+      isSigner: false,
+      isWritable: !!pubkey && isWritable,
+    })
+  );
+
+  const commandDataLayout = BufferLayout.struct([
+    BufferLayout.u8("instruction"),
+    BufferLayout.nu64("amount"),
+    BufferLayout.nu64("limit"),
+  ]);
+
+  return new TransactionInstruction({
+    keys,
+    programId: poolProgramId,
+    data: encodeData(commandDataLayout, { instruction: 3, amount, limit }),
+  });
+}
+
+export const freezePoolInstruction = (
+  ownerAccount: PublicKey,
+  poolAccount: PublicKey,
+  poolProgramId: PublicKey,
+  signers: PublicKey[] = [],
+): TransactionInstruction =>
+  ownerInstruction(ownerAccount, poolAccount, signers, poolProgramId, tagData(4));
+
+export const thawPoolInstruction = (
+  ownerAccount: PublicKey,
+  poolAccount: PublicKey,
+  poolProgramId: PublicKey,
+  signers: PublicKey[] = [],
+): TransactionInstruction =>
+  ownerInstruction(ownerAccount, poolAccount, signers, poolProgramId, tagData(5));
+
+export const earnInstruction = (
+  ownerAccount: PublicKey,
+  poolAccount: PublicKey,
+  vaultAccount: PublicKey,
+  mintAccount: PublicKey,
+  dstAccount: PublicKey,
+  treasurerAccount: PublicKey,
+  spltProgramId: PublicKey,
+  splt2022ProgramId: PublicKey,
+  amount: number,
+  poolProgramId: PublicKey,
+  signers: PublicKey[] = [],
+): TransactionInstruction => {
+  const { head, tail } = ownerKeys(ownerAccount, poolAccount, signers);
+  const keys = [
+    ...head,
+    { pubkey: vaultAccount, isSigner: false, isWritable: true },
+    { pubkey: mintAccount, isSigner: false, isWritable: false },
+    { pubkey: dstAccount, isSigner: false, isWritable: true },
+    { pubkey: treasurerAccount, isSigner: false, isWritable: false },
+    { pubkey: spltProgramId, isSigner: false, isWritable: false },
+    { pubkey: splt2022ProgramId, isSigner: false, isWritable: false },
+    ...tail,
+  ];
+  return new TransactionInstruction({
+    keys,
+    programId: poolProgramId,
+    data: u64Data(6, amount),
+  });
+}
+
+export const transferPoolOwnershipInstruction = (
+  ownerAccount: PublicKey,
+  poolAccount: PublicKey,
+  newOwnerAccount: PublicKey,
+  poolProgramId: PublicKey,
+  signers: PublicKey[] = [],
+): TransactionInstruction =>
+  ownerInstruction(ownerAccount, poolAccount, signers, poolProgramId, tagData(7), [
+    newOwnerAccount,
+  ]);
 
+export const initializeMultisigInstruction = (
+  payerAccount: PublicKey,
+  multisigAccount: PublicKey, // fresh keypair, signs the transaction
+  members: PublicKey[],
+  m: number,
+  poolProgramId: PublicKey,
+): TransactionInstruction => {
+  const keys = [
+    { pubkey: payerAccount, isSigner: true, isWritable: true },
+    { pubkey: multisigAccount, isSigner: true, isWritable: true },
+    ...members.map((pubkey) => ({ pubkey, isSigner: false, isWritable: false })),
+  ];
   return new TransactionInstruction({
     keys,
     programId: poolProgramId,
-    data,
+    data: u8Data(8, m),
   });
-}
\ No newline at end of file
+}
+
+export const togglePauseInstruction = (
+  ownerAccount: PublicKey,
+  poolAccount: PublicKey,
+  flags: number, // swap, deposit, withdraw bits
+  poolProgramId: PublicKey,
+  signers: PublicKey[] = [],
+): TransactionInstruction =>
+  ownerInstruction(ownerAccount, poolAccount, signers, poolProgramId, u8Data(9, flags));
+
+export const updatePoolStateInstruction = (
+  ownerAccount: PublicKey,
+  poolAccount: PublicKey,
+  state: number, // 1 initialized, 2 withdraw-only, 3 deprecated
+  poolProgramId: PublicKey,
+  signers: PublicKey[] = [],
+): TransactionInstruction =>
+  ownerInstruction(ownerAccount, poolAccount, signers, poolProgramId, u8Data(10, state));
+
+export const addPoolTokenInstruction = (
+  payerAccount: PublicKey,
+  ownerAccount: PublicKey,
+  poolAccount: PublicKey,
+  lptAccount: PublicKey,
+  mintLptAccount: PublicKey,
+  srcAccount: PublicKey,
+  mintAccount: PublicKey,
+  treasuryAccount: PublicKey,
+  vaultAccount: PublicKey, // fresh keypair, signs the transaction
+  minimumAccount: PublicKey, // derived from the mint, may not exist
+  treasurerAccount: PublicKey,
+  sysProgramId: PublicKey,
+  spltProgramId: PublicKey,
+  splt2022ProgramId: PublicKey,
+  sysVarRentAccount: PublicKey,
+  splAtaProgramId: PublicKey,
+  amount: number,
+  value: number, // the amount quoted in token 0
+  minLpt: number,
+  poolProgramId: PublicKey,
+  signers: PublicKey[] = [],
+): TransactionInstruction => {
+  const { head, tail } = ownerKeys(ownerAccount, poolAccount, signers);
+  const keys = [
+    { pubkey: payerAccount, isSigner: true, isWritable: true },
+    ...head,
+    { pubkey: lptAccount, isSigner: false, isWritable: true },
+    { pubkey: mintLptAccount, isSigner: false, isWritable: true },
+    { pubkey: srcAccount, isSigner: false, isWritable: true },
+    { pubkey: mintAccount, isSigner: false, isWritable: false },
+    { pubkey: treasuryAccount, isSigner: false, isWritable: true },
+    { pubkey: vaultAccount, isSigner: true, isWritable: true },
+    { pubkey: minimumAccount, isSigner: false, isWritable: false },
+    { pubkey: treasurerAccount, isSigner: false, isWritable: false },
+    { pubkey: sysProgramId, isSigner: false, isWritable: false },
+    { pubkey: spltProgramId, isSigner: false, isWritable: false },
+    { pubkey: splt2022ProgramId, isSigner: false, isWritable: false },
+    { pubkey: sysVarRentAccount, isSigner: false, isWritable: false },
+    { pubkey: splAtaProgramId, isSigner: false, isWritable: false },
+    ...tail,
+  ];
+  const commandDataLayout = BufferLayout.struct([
+    BufferLayout.u8("instruction"),
+    BufferLayout.nu64("amount"),
+    BufferLayout.nu64("value"),
+    BufferLayout.nu64("min_lpt"),
+  ]);
+
+  return new TransactionInstruction({
+    keys,
+    programId: poolProgramId,
+    data: encodeData(commandDataLayout, {
+      instruction: 11,
+      amount,
+      value,
+      min_lpt: minLpt,
+    }),
+  });
+}
+
+export const removePoolTokenInstruction = (
+  ownerAccount: PublicKey,
+  poolAccount: PublicKey,
+  treasuryAccount: PublicKey, // of a retired token whose reserve is out
+  vaultAccount: PublicKey,
+  mintAccount: PublicKey,
+  dstTokenAccount: PublicKey, // receives the treasury and vault balances
+  dstAccount: PublicKey, // receives the treasury and vault rent
+  treasurerAccount: PublicKey,
+  spltProgramId: PublicKey,
+  splt2022ProgramId: PublicKey,
+  poolProgramId: PublicKey,
+  signers: PublicKey[] = [],
+): TransactionInstruction => {
+  const { head, tail } = ownerKeys(ownerAccount, poolAccount, signers);
+  const keys = [
+    ...head,
+    { pubkey: treasuryAccount, isSigner: false, isWritable: true },
+    { pubkey: vaultAccount, isSigner: false, isWritable: true },
+    { pubkey: mintAccount, isSigner: false, isWritable: true },
+    { pubkey: dstTokenAccount, isSigner: false, isWritable: true },
+    { pubkey: dstAccount, isSigner: false, isWritable: true },
+    { pubkey: treasurerAccount, isSigner: false, isWritable: false },
+    { pubkey: spltProgramId, isSigner: false, isWritable: false },
+    { pubkey: splt2022ProgramId, isSigner: false, isWritable: false },
+    ...tail,
+  ];
+  return new TransactionInstruction({
+    keys,
+    programId: poolProgramId,
+    data: tagData(12),
+  });
+}
+
+export const closePoolInstruction = (
+  payerAccount: PublicKey, // funds the token accounts of the locker
+  ownerAccount: PublicKey,
+  poolAccount: PublicKey,
+  mintLptAccount: PublicKey,
+  dstAccount: PublicKey, // receives all the lamports
+  lockerAccount: PublicKey, // from findLockerAddress
+  treasurerAccount: PublicKey,
+  spltProgramId: PublicKey,
+  splt2022ProgramId: PublicKey,
+  sysProgramId: PublicKey,
+  splAtaProgramId: PublicKey,
+  tokens: ClosePoolToken[],
+  poolProgramId: PublicKey,
+  signers: PublicKey[] = [],
+): TransactionInstruction => {
+  const { head, tail } = ownerKeys(ownerAccount, poolAccount, signers);
+  const keys = [
+    { pubkey: payerAccount, isSigner: true, isWritable: true },
+    ...head,
+    { pubkey: mintLptAccount, isSigner: false, isWritable: false },
+    { pubkey: dstAccount, isSigner: false, isWritable: true },
+    { pubkey: lockerAccount, isSigner: false, isWritable: false },
+    { pubkey: treasurerAccount, isSigner: false, isWritable: false },
+    { pubkey: spltProgramId, isSigner: false, isWritable: false },
+    { pubkey: splt2022ProgramId, isSigner: false, isWritable: false },
+    { pubkey: sysProgramId, isSigner: false, isWritable: false },
+    { pubkey: splAtaProgramId, isSigner: false, isWritable: false },
+    ...flatKeys(tokens, (token) => [
+      { pubkey: token.treasuryAccount, isSigner: false, isWritable: true },
+      { pubkey: token.vaultAccount, isSigner: false, isWritable: true },
+      { pubkey: token.mintAccount, isSigner: false, isWritable: true },
+      { pubkey: token.lockTokenAccount, isSigner: false, isWritable: true },
+      { pubkey: token.dstTokenAccount, isSigner: false, isWritable: true },
+    ]),
+    ...tail,
+  ];
+  return new TransactionInstruction({
+    keys,
+    programId: poolProgramId,
+    data: tagData(13),
+  });
+}
+
+export const addLPBalancedInstruction = (
+  ownerAccount: PublicKey,
+  poolAccount: PublicKey,
+  lptAccount: PublicKey,
+  mintLptAccount: PublicKey,
+  treasurerAccount: PublicKey,
+  spltProgramId: PublicKey,
+  splt2022ProgramId: PublicKey,
+  tokens: LiquidityToken[],
+  lptOut: number,
+  maxAmounts: number[],
+  poolProgramId: PublicKey,
+): TransactionInstruction => {
+  const keys = [
+    { pubkey: ownerAccount, isSigner: true, isWritable: false },
+    { pubkey: poolAccount, isSigner: false, isWritable: true },
+    { pubkey: lptAccount, isSigner: false, isWritable: true },
+    { pubkey: mintLptAccount, isSigner: false, isWritable: true },
+    { pubkey: treasurerAccount, isSigner: false, isWritable: false },
+    { pubkey: spltProgramId, isSigner: false, isWritable: false },
+    { pubkey: splt2022ProgramId, isSigner: false, isWritable: false },
+    ...liquidityTokenKeys(tokens),
+  ];
+
+  const commandDataLayout = BufferLayout.struct([
+    BufferLayout.u8("instruction"),
+    BufferLayout.nu64("lpt_out"),
+    amountsLayout(maxAmounts.length, "max_amounts"),
+  ]);
+
+  return new TransactionInstruction({
+    keys,
+    programId: poolProgramId,
+    data: encodeData(commandDataLayout, {
+      instruction: 14,
+      lpt_out: lptOut,
+      max_amounts: maxAmounts,
+    }),
+  });
+}
+
+export const removeLPImbalancedInstruction = (
+  ownerAccount: PublicKey,
+  poolAccount: PublicKey,
+  lptAccount: PublicKey,
+  mintLptAccount: PublicKey,
+  treasurerAccount: PublicKey,
+  spltProgramId: PublicKey,
+  splt2022ProgramId: PublicKey,
+  tokens: LiquidityToken[],
+  amounts: number[],
+  maxLpt: number,
+  poolProgramId: PublicKey,
+): TransactionInstruction => {
+  const keys = [
+    { pubkey: ownerAccount, isSigner: true, isWritable: false },
+    { pubkey: poolAccount, isSigner: false, isWritable: true },
+    { pubkey: lptAccount, isSigner: false, isWritable: true },
+    { pubkey: mintLptAccount, isSigner: false, isWritable: true },
+    { pubkey: treasurerAccount, isSigner: false, isWritable: false },
+    { pubkey: spltProgramId, isSigner: false, isWritable: false },
+    { pubkey: splt2022ProgramId, isSigner: false, isWritable: false },
+    ...liquidityTokenKeys(tokens),
+  ];
+
+  // The amounts come first, the LPT bound takes the last 8 bytes
+  const commandDataLayout = BufferLayout.struct([
+    BufferLayout.u8("instruction"),
+    amountsLayout(amounts.length, "amounts"),
+    BufferLayout.nu64("max_lpt"),
+  ]);
+
+  return new TransactionInstruction({
+    keys,
+    programId: poolProgramId,
+    data: encodeData(commandDataLayout, {
+      instruction: 15,
+      amounts,
+      max_lpt: maxLpt,
+    }),
+  });
+}
+
+export const initializeAllowlistInstruction = (
+  ownerAccount: PublicKey,
+  poolAccount: PublicKey,
+  allowlistAccount: PublicKey, // fresh keypair, signs the transaction
+  earning: number,
+  poolProgramId: PublicKey,
+  signers: PublicKey[] = [],
+): TransactionInstruction => {
+  const { head, tail } = ownerKeys(ownerAccount, poolAccount, signers);
+  const keys = [
+    ...head,
+    { pubkey: allowlistAccount, isSigner: true, isWritable: true },
+    ...tail,
+  ];
+  return new TransactionInstruction({
+    keys,
+    programId: poolProgramId,
+    data: u64Data(16, earning),
+  });
+}
+
+const allowlistInstruction = (
+  ownerAccount: PublicKey,
+  poolAccount: PublicKey,
+  allowlistAccount: PublicKey,
+  traderAccount: PublicKey | undefined,
+  signers: PublicKey[],
+  poolProgramId: PublicKey,
+  data: Buffer,
+): TransactionInstruction => {
+  const { head, tail } = ownerKeys(ownerAccount, poolAccount, signers);
+  const keys = [
+    ...head,
+    { pubkey: allowlistAccount, isSigner: false, isWritable: true },
+    ...(traderAccount
+      ? [{ pubkey: traderAccount, isSigner: false, isWritable: false }]
+      : []),
+    ...tail,
+  ];
+  return new TransactionInstruction({ keys, programId: poolProgramId, data });
+};
+
+export const addAllowlistTraderInstruction = (
+  ownerAccount: PublicKey,
+  poolAccount: PublicKey,
+  allowlistAccount: PublicKey,
+  traderAccount: PublicKey,
+  poolProgramId: PublicKey,
+  signers: PublicKey[] = [],
+): TransactionInstruction =>
+  allowlistInstruction(
+    ownerAccount,
+    poolAccount,
+    allowlistAccount,
+    traderAccount,
+    signers,
+    poolProgramId,
+    tagData(17)
+  );
+
+export const removeAllowlistTraderInstruction = (
+  ownerAccount: PublicKey,
+  poolAccount: PublicKey,
+  allowlistAccount: PublicKey,
+  traderAccount: PublicKey,
+  poolProgramId: PublicKey,
+  signers: PublicKey[] = [],
+): TransactionInstruction =>
+  allowlistInstruction(
+    ownerAccount,
+    poolAccount,
+    allowlistAccount,
+    traderAccount,
+    signers,
+    poolProgramId,
+    tagData(18)
+  );
+
+export const setAllowlistEarningInstruction = (
+  ownerAccount: PublicKey,
+  poolAccount: PublicKey,
+  allowlistAccount: PublicKey,
+  earning: number,
+  poolProgramId: PublicKey,
+  signers: PublicKey[] = [],
+): TransactionInstruction =>
+  allowlistInstruction(
+    ownerAccount,
+    poolAccount,
+    allowlistAccount,
+    undefined,
+    signers,
+    poolProgramId,
+    u64Data(19, earning)
+  );
+
+export const setReferralShareInstruction = (
+  ownerAccount: PublicKey,
+  poolAccount: PublicKey,
+  share: number,
+  poolProgramId: PublicKey,
+  signers: PublicKey[] = [],
+): TransactionInstruction =>
+  ownerInstruction(ownerAccount, poolAccount, signers, poolProgramId, u64Data(20, share));
+
+export const setFeeTiersInstruction = (
+  ownerAccount: PublicKey,
+  poolAccount: PublicKey,
+  feeTiers: Tier[], // threshold is the volume
+  poolProgramId: PublicKey,
+  signers: PublicKey[] = [],
+): TransactionInstruction =>
+  ownerInstruction(
+    ownerAccount,
+    poolAccount,
+    signers,
+    poolProgramId,
+    tiersData(21, feeTiers)
+  );
+
+export const initializeTraderVolumeInstruction = (
+  traderAccount: PublicKey,
+  poolAccount: PublicKey,
+  volumeAccount: PublicKey, // fresh keypair, signs the transaction
+  poolProgramId: PublicKey,
+): TransactionInstruction => {
+  const keys = [
+    { pubkey: traderAccount, isSigner: true, isWritable: true },
+    { pubkey: poolAccount, isSigner: false, isWritable: false },
+    { pubkey: volumeAccount, isSigner: true, isWritable: true },
+  ];
+  return new TransactionInstruction({
+    keys,
+    programId: poolProgramId,
+    data: tagData(22),
+  });
+}
+
+export const setHolderTiersInstruction = (
+  ownerAccount: PublicKey,
+  poolAccount: PublicKey,
+  discountMintAccount: PublicKey,
+  holderTiers: Tier[], // threshold is the balance
+  poolProgramId: PublicKey,
+  signers: PublicKey[] = [],
+): TransactionInstruction =>
+  ownerInstruction(
+    ownerAccount,
+    poolAccount,
+    signers,
+    poolProgramId,
+    tiersData(23, holderTiers),
+    [discountMintAccount]
+  );
+
+export const setDynamicFeeInstruction = (
+  ownerAccount: PublicKey,
+  poolAccount: PublicKey,
+  isEnabled: boolean,
+  minFee: number,
+  maxFee: number,
+  poolProgramId: PublicKey,
+  signers: PublicKey[] = [],
+): TransactionInstruction => {
+  const commandDataLayout = BufferLayout.struct([
+    BufferLayout.u8("instruction"),
+    BufferLayout.u8("is_enabled"),
+    BufferLayout.nu64("min_fee"),
+    BufferLayout.nu64("max_fee"),
+  ]);
+  const data = encodeData(commandDataLayout, {
+    instruction: 24,
+    is_enabled: isEnabled ? 1 : 0,
+    min_fee: minFee,
+    max_fee: maxFee,
+  });
+  return ownerInstruction(ownerAccount, poolAccount, signers, poolProgramId, data);
+}
+
+export const setCircuitBreakerInstruction = (
+  ownerAccount: PublicKey,
+  poolAccount: PublicKey,
+  maxDeviation: number, // basis points, 0 turns the breaker off
+  poolProgramId: PublicKey,
+  signers: PublicKey[] = [],
+): TransactionInstruction => {
+  const commandDataLayout = BufferLayout.struct([
+    BufferLayout.u8("instruction"),
+    BufferLayout.u16("max_deviation"),
+  ]);
+  const data = encodeData(commandDataLayout, {
+    instruction: 25,
+    max_deviation: maxDeviation,
+  });
+  return ownerInstruction(ownerAccount, poolAccount, signers, poolProgramId, data);
+}
+
+export const setTradeLimitsInstruction = (
+  ownerAccount: PublicKey,
+  poolAccount: PublicKey,
+  maxBidRatio: number, // basis points, 0 means no cap
+  maxAskRatio: number,
+  poolProgramId: PublicKey,
+  signers: PublicKey[] = [],
+): TransactionInstruction => {
+  const commandDataLayout = BufferLayout.struct([
+    BufferLayout.u8("instruction"),
+    BufferLayout.u16("max_bid_ratio"),
+    BufferLayout.u16("max_ask_ratio"),
+  ]);
+  const data = encodeData(commandDataLayout, {
+    instruction: 26,
+    max_bid_ratio: maxBidRatio,
+    max_ask_ratio: maxAskRatio,
+  });
+  return ownerInstruction(ownerAccount, poolAccount, signers, poolProgramId, data);
+}
+
+export const setAntiSandwichInstruction = (
+  ownerAccount: PublicKey,
+  poolAccount: PublicKey,
+  isEnabled: boolean,
+  poolProgramId: PublicKey,
+  signers: PublicKey[] = [],
+): TransactionInstruction =>
+  ownerInstruction(
+    ownerAccount,
+    poolAccount,
+    signers,
+    poolProgramId,
+    u8Data(27, isEnabled ? 1 : 0)
+  );
+
+export const initializeRouterRegistryInstruction = (
+  ownerAccount: PublicKey,
+  poolAccount: PublicKey,
+  registryAccount: PublicKey, // fresh keypair, signs the transaction
+  poolProgramId: PublicKey,
+  signers: PublicKey[] = [],
+): TransactionInstruction => {
+  const { head, tail } = ownerKeys(ownerAccount, poolAccount, signers);
+  const keys = [
+    ...head,
+    { pubkey: registryAccount, isSigner: true, isWritable: true },
+    ...tail,
+  ];
+  return new TransactionInstruction({
+    keys,
+    programId: poolProgramId,
+    data: tagData(28),
+  });
+}
+
+const routerInstruction = (
+  ownerAccount: PublicKey,
+  poolAccount: PublicKey,
+  registryAccount: PublicKey,
+  routerProgramId: PublicKey,
+  signers: PublicKey[],
+  poolProgramId: PublicKey,
+  data: Buffer,
+): TransactionInstruction => {
+  const { head, tail } = ownerKeys(ownerAccount, poolAccount, signers);
+  const keys = [
+    ...head,
+    { pubkey: registryAccount, isSigner: false, isWritable: true },
+    { pubkey: routerProgramId, isSigner: false, isWritable: false },
+    ...tail,
+  ];
+  return new TransactionInstruction({ keys, programId: poolProgramId, data });
+};
+
+export const addRouterInstruction = (
+  ownerAccount: PublicKey,
+  poolAccount: PublicKey,
+  registryAccount: PublicKey,
+  routerProgramId: PublicKey,
+  poolProgramId: PublicKey,
+  signers: PublicKey[] = [],
+): TransactionInstruction =>
+  routerInstruction(
+    ownerAccount,
+    poolAccount,
+    registryAccount,
+    routerProgramId,
+    signers,
+    poolProgramId,
+    tagData(29)
+  );
+
+export const removeRouterInstruction = (
+  ownerAccount: PublicKey,
+  poolAccount: PublicKey,
+  registryAccount: PublicKey,
+  routerProgramId: PublicKey,
+  poolProgramId: PublicKey,
+  signers: PublicKey[] = [],
+): TransactionInstruction =>
+  routerInstruction(
+    ownerAccount,
+    poolAccount,
+    registryAccount,
+    routerProgramId,
+    signers,
+    poolProgramId,
+    tagData(30)
+  );
+
+export const setPricePegInstruction = (
+  ownerAccount: PublicKey,
+  poolAccount: PublicKey,
+  priceAccount: PublicKey | undefined, // undefined unpegs the pool
+  maxStaleness: number, // slots
+  spread: number, // basis points
+  maxConfidence: number, // basis points of the price
+  poolProgramId: PublicKey,
+  signers: PublicKey[] = [],
+): TransactionInstruction => {
+  const commandDataLayout = BufferLayout.struct([
+    BufferLayout.u8("instruction"),
+    BufferLayout.nu64("max_staleness"),
+    BufferLayout.u16("spread"),
+    BufferLayout.u16("max_confidence"),
+  ]);
+  const data = encodeData(commandDataLayout, {
+    instruction: 31,
+    max_staleness: maxStaleness,
+    spread,
+    max_confidence: maxConfidence,
+  });
+  return ownerInstruction(ownerAccount, poolAccount, signers, poolProgramId, data, [
+    priceAccount || poolProgramId,
+  ]);
+}
+
+export const initializeRateProviderInstruction = (
+  updaterAccount: PublicKey,
+  rateAccount: PublicKey, // fresh keypair, signs the transaction
+  rate: number,
+  poolProgramId: PublicKey,
+): TransactionInstruction => {
+  const keys = [
+    { pubkey: updaterAccount, isSigner: true, isWritable: true },
+    { pubkey: rateAccount, isSigner: true, isWritable: true },
+  ];
+  return new TransactionInstruction({
+    keys,
+    programId: poolProgramId,
+    data: u64Data(32, rate),
+  });
+}
+
+export const updateRateInstruction = (
+  updaterAccount: PublicKey,
+  rateAccount: PublicKey,
+  rate: number,
+  poolProgramId: PublicKey,
+): TransactionInstruction => {
+  const keys = [
+    { pubkey: updaterAccount, isSigner: true, isWritable: false },
+    { pubkey: rateAccount, isSigner: false, isWritable: true },
+  ];
+  return new TransactionInstruction({
+    keys,
+    programId: poolProgramId,
+    data: u64Data(33, rate),
+  });
+}
+
+export const setPoolRateProviderInstruction = (
+  ownerAccount: PublicKey,
+  poolAccount: PublicKey,
+  treasuryAccount: PublicKey,
+  rateAccount: PublicKey | undefined, // undefined drops the provider
+  rateDuration: number, // seconds a provider rate stays valid after its update
+  poolProgramId: PublicKey,
+  signers: PublicKey[] = [],
+): TransactionInstruction => {
+  const commandDataLayout = BufferLayout.struct([
+    BufferLayout.u8("instruction"),
+    BufferLayout.ns64("rate_duration"),
+  ]);
+  const data = encodeData(commandDataLayout, {
+    instruction: 34,
+    rate_duration: rateDuration,
+  });
+  return ownerInstruction(ownerAccount, poolAccount, signers, poolProgramId, data, [
+    treasuryAccount,
+    rateAccount || poolProgramId,
+  ]);
+}
+
+export const refreshRatesInstruction = (
+  poolAccount: PublicKey,
+  rateAccounts: PublicKey[], // any of the pool rate providers
+  poolProgramId: PublicKey,
+): TransactionInstruction => {
+  const keys = [
+    { pubkey: poolAccount, isSigner: false, isWritable: true },
+    ...rateAccounts.map((pubkey) => ({ pubkey, isSigner: false, isWritable: false })),
+  ];
+  return new TransactionInstruction({
+    keys,
+    programId: poolProgramId,
+    data: tagData(35),
+  });
+}
+
+export const setCurveInstruction = (
+  ownerAccount: PublicKey,
+  poolAccount: PublicKey,
+  curve: 0 | 1, // constant product, constant sum
+  poolProgramId: PublicKey,
+  signers: PublicKey[] = [],
+): TransactionInstruction =>
+  ownerInstruction(ownerAccount, poolAccount, signers, poolProgramId, u8Data(36, curve));
+
+export const setEarningInstruction = (
+  ownerAccount: PublicKey,
+  poolAccount: PublicKey,
+  isEnabled: boolean,
+  poolProgramId: PublicKey,
+  signers: PublicKey[] = [],
+): TransactionInstruction =>
+  ownerInstruction(
+    ownerAccount,
+    poolAccount,
+    signers,
+    poolProgramId,
+    u8Data(37, isEnabled ? 1 : 0)
+  );
+
+export const setMinimumReserveInstruction = (
+  authorityAccount: PublicKey, // mint authority, pays for the minimum account
+  mintAccount: PublicKey,
+  minimumAccount: PublicKey, // from findMinimumReserveAddress
+  sysProgramId: PublicKey,
+  sysVarRentAccount: PublicKey,
+  minimum: number,
+  poolProgramId: PublicKey,
+): TransactionInstruction => {
+  const keys = [
+    { pubkey: authorityAccount, isSigner: true, isWritable: true },
+    { pubkey: mintAccount, isSigner: false, isWritable: false },
+    { pubkey: minimumAccount, isSigner: false, isWritable: true },
+    { pubkey: sysProgramId, isSigner: false, isWritable: false },
+    { pubkey: sysVarRentAccount, isSigner: false, isWritable: false },
+  ];
+  return new TransactionInstruction({
+    keys,
+    programId: poolProgramId,
+    data: u64Data(38, minimum),
+  });
+}
+
+export const retirePoolTokenInstruction = (
+  ownerAccount: PublicKey,
+  poolAccount: PublicKey,
+  mintLptAccount: PublicKey,
+  treasuryAccount: PublicKey,
+  poolProgramId: PublicKey,
+  signers: PublicKey[] = [],
+): TransactionInstruction =>
+  ownerInstruction(ownerAccount, poolAccount, signers, poolProgramId, tagData(39), [
+    mintLptAccount,
+    treasuryAccount,
+  ]);
+
+export const exitPoolTokenInstruction = (
+  ownerAccount: PublicKey,
+  poolAccount: PublicKey,
+  lptAccount: PublicKey,
+  mintLptAccount: PublicKey,
+  dstAccount: PublicKey, // created as the owner's associated account when missing
+  mintAccount: PublicKey,
+  treasuryAccount: PublicKey, // of the retiring token
+  treasurerAccount: PublicKey,
+  spltProgramId: PublicKey,
+  splt2022ProgramId: PublicKey,
+  sysProgramId: PublicKey,
+  splAtaProgramId: PublicKey,
+  amount: number,
+  maxLpt: number,
+  poolProgramId: PublicKey,
+): TransactionInstruction => {
+  const keys = [
+    { pubkey: ownerAccount, isSigner: true, isWritable: true },
+    { pubkey: poolAccount, isSigner: false, isWritable: true },
+    { pubkey: lptAccount, isSigner: false, isWritable: true },
+    { pubkey: mintLptAccount, isSigner: false, isWritable: true },
+    { pubkey: dstAccount, isSigner: false, isWritable: true },
+    { pubkey: mintAccount, isSigner: false, isWritable: false },
+    { pubkey: treasuryAccount, isSigner: false, isWritable: true },
+    { pubkey: treasurerAccount, isSigner: false, isWritable: false },
+    { pubkey: spltProgramId, isSigner: false, isWritable: false },
+    { pubkey: splt2022ProgramId, isSigner: false, isWritable: false },
+    { pubkey: sysProgramId, isSigner: false, isWritable: false },
+    { pubkey: splAtaProgramId, isSigner: false, isWritable: false },
+  ];
+
+  const commandDataLayout = BufferLayout.struct([
+    BufferLayout.u8("instruction"),
+    BufferLayout.nu64("amount"),
+    BufferLayout.nu64("max_lpt"),
+  ]);
+
+  return new TransactionInstruction({
+    keys,
+    programId: poolProgramId,
+    data: encodeData(commandDataLayout, {
+      instruction: 40,
+      amount,
+      max_lpt: maxLpt,
+    }),
+  });
+}
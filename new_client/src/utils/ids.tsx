@@ -7,6 +7,10 @@ export const TOKEN_PROGRAM_ID = new PublicKey(
   "TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA"
 );
 
+export const TOKEN_2022_PROGRAM_ID = new PublicKey(
+  "TokenzQdBNbLqP5VEhdkAS6EPFLC1PHnBqCXEpPxuEb"
+);
+
 export const SPL_ASSOCIATED_TOKEN_ACCOUNT_PROGRAM_ID: PublicKey = new PublicKey(
   'ATokenGPvbdGVxr1b2hvZbsiqW5xWH25efTNsLJA8knL',
 );
@@ -67,5 +71,6 @@ export const programIds = () => {
     ata: SPL_ASSOCIATED_TOKEN_ACCOUNT_PROGRAM_ID,
     swap: SWAP_PROGRAM_ID,
     token: TOKEN_PROGRAM_ID,
+    token2022: TOKEN_2022_PROGRAM_ID,
   };
 };
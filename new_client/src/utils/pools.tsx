@@ -1,4 +1,5 @@
 import {
+  AccountInfo,
   Connection,
   Keypair,
   PublicKey,
@@ -27,6 +28,9 @@ import { sendTransaction } from "utils/connection";
 
 import {
   createInitPoolInstruction,
+  findLockerAddress,
+  findMinimumReserveAddress,
+  InitPoolToken,
   LiquidityComponent,
   PoolConfig,
   PoolInfo,
@@ -714,6 +718,46 @@ async function createTreasurerPubkey(
   return expectedAcc; // this is Treasurer Account's PDA
 }
 
+// Associated accounts are derived under the program owning the mint, legacy or Token-2022
+async function deriveAssociatedTokenAddress(
+  owner: PublicKey,
+  mint: PublicKey,
+  tokenProgramId: PublicKey = programIds().token
+): Promise<PublicKey> {
+  const [address] = await PublicKey.findProgramAddress(
+    [owner.toBuffer(), tokenProgramId.toBuffer(), mint.toBuffer()],
+    programIds().ata
+  );
+  return address;
+}
+
+// Token-2022 mints write their extensions as (type: u16, length: u16, value) entries after the
+// account type, and token accounts of a transfer-fee mint carry the withheld fees (u64) the same way.
+// The other extensions the pool accepts need no room in the token accounts
+const ACCOUNT_TYPE_SIZE = 1;
+const EXTENSION_HEADER_SIZE = 4;
+const TRANSFER_FEE_CONFIG_EXTENSION = 1;
+const TRANSFER_FEE_AMOUNT_SIZE = 8;
+
+function getAccountLenForMint(mintInfo: AccountInfo<Buffer>): number {
+  const { data } = mintInfo;
+  let offset = AccountLayout.span + ACCOUNT_TYPE_SIZE;
+  while (offset + EXTENSION_HEADER_SIZE <= data.length) {
+    const extensionType = data.readUInt16LE(offset);
+    const extensionLength = data.readUInt16LE(offset + 2);
+    if (extensionType === TRANSFER_FEE_CONFIG_EXTENSION) {
+      return (
+        AccountLayout.span +
+        ACCOUNT_TYPE_SIZE +
+        EXTENSION_HEADER_SIZE +
+        TRANSFER_FEE_AMOUNT_SIZE
+      );
+    }
+    offset += EXTENSION_HEADER_SIZE + extensionLength;
+  }
+  return AccountLayout.span;
+}
+
 async function _addLiquidityNewPool(
   walletPubKey: PublicKey,
   signTransaction: SignerWalletAdapterProps["signTransaction"],
@@ -768,43 +812,30 @@ async function _addLiquidityNewPool(
       programIds().swap
     );
     console.log("_addLiquidityNewPool 3");
+    // The program creates the treasuries as associated accounts of the treasurer, and the
+    // vaults it initializes belong to the program owning each mint, sized for its extensions
+    const mintInfos: AccountInfo<Buffer>[] = [];
+    for (const leg of components) {
+      const mintInfo = await connection.getAccountInfo(
+        new PublicKey(leg.mintAddress)
+      );
+      if (!mintInfo) {
+        console.log("_addLiquidityNewPool - mint is missing");
+        return;
+      }
+      mintInfos.push(mintInfo);
+    }
     const accountRentExempt =
       await connection.getMinimumBalanceForRentExemption(AccountLayout.span);
-    // Create holding accounts for
-    const holdingAccounts: Keypair[] = [];
     let signers: Keypair[] = [];
 
-    components.forEach((leg) => {
-      if (!leg.account) {
-        return;
-      }
-
-      const mintPublicKey = leg.mintAddress;
-
-      // component account to store tokens I of N in liquidity pool
-      holdingAccounts.push(
-        createSplAccount(
-          transactions,
-          walletPubKey,
-          accountRentExempt,
-          new PublicKey(mintPublicKey),
-          authority_PDA_poolAccount,
-          AccountLayout.span
-        )
-      );
-    });
-
     console.log("_addLiquidityNewPool 4");
     let txId = await sendTransaction(
       connection,
       walletPubKey,
       transactions,
       signTransaction,
-      [
-        liquidityTokenAccount,
-        ...holdingAccounts,
-        ...signers,
-      ]
+      [liquidityTokenAccount, ...signers]
     );
 
     if (txId == null) {
@@ -828,14 +859,22 @@ async function _addLiquidityNewPool(
       })
     );
     
-    const vaultKeyPair = createSplAccount(
-      transactions,
-      walletPubKey,
-      accountRentExempt,
-      liquidityTokenAccount.publicKey,
-      poolAccount.publicKey,
-      AccountLayout.span
-    );
+    // Every token gets a vault for the earnings charged in it, the program initializes them
+    const vaultKeyPairs: Keypair[] = [];
+    for (const mintInfo of mintInfos) {
+      const vaultKeyPair = Keypair.generate();
+      const space = getAccountLenForMint(mintInfo);
+      transactions.add(
+        SystemProgram.createAccount({
+          fromPubkey: walletPubKey,
+          newAccountPubkey: vaultKeyPair.publicKey,
+          lamports: await connection.getMinimumBalanceForRentExemption(space),
+          space,
+          programId: mintInfo.owner,
+        })
+      );
+      vaultKeyPairs.push(vaultKeyPair);
+    }
 
     const proof_for_freezeKeyPair = createSplAccount(
       transactions,
@@ -846,13 +885,36 @@ async function _addLiquidityNewPool(
       AccountLayout.span      
     );
 
+    // The locked liquidity sits in the locker's associated account
+    const lockerAccount = await findLockerAddress(
+      poolAccount.publicKey,
+      programIds().swap
+    );
+    const lockAccount = await deriveAssociatedTokenAddress(
+      lockerAccount,
+      liquidityTokenAccount.publicKey
+    );
+
     console.log("_addLiquidityNewPool 6");
-    if (
-      !components[0].account ||
-      !components[1].account ||
-      !components[2].account
-    )
-      return;
+    if (components.some((c) => !c.account)) return;
+    const tokens: InitPoolToken[] = [];
+    for (let i = 0; i < components.length; i++) {
+      const mintAccount = new PublicKey(components[i].mintAddress);
+      tokens.push({
+        srcAccount: components[i].account as PublicKey,
+        mintAccount,
+        treasuryAccount: await deriveAssociatedTokenAddress(
+          treasurerAccount,
+          mintAccount,
+          mintInfos[i].owner
+        ),
+        vaultAccount: vaultKeyPairs[i].publicKey,
+        minimumAccount: await findMinimumReserveAddress(
+          mintAccount,
+          programIds().swap
+        ),
+      });
+    }
     console.log("_addLiquidityNewPool 7");
     transactions.add(
       createInitPoolInstruction(
@@ -861,26 +923,18 @@ async function _addLiquidityNewPool(
         poolAccount.publicKey,
         authority_PDA_poolAccount,
         liquidityTokenAccount.publicKey,
-        vaultKeyPair.publicKey,
         proof_for_freezeKeyPair.publicKey,
-        components[0].account,
-        new PublicKey(components[0].mintAddress),
-        holdingAccounts[0].publicKey,
-        components[1].account,
-        new PublicKey(components[1].mintAddress),
-        holdingAccounts[1].publicKey,
-        components[2].account,
-        new PublicKey(components[2].mintAddress),
-        holdingAccounts[2].publicKey,
+        lockAccount,
+        lockerAccount,
         treasurerAccount,
         SystemProgram.programId,
         programIds().token,
+        programIds().token2022,
         new PublicKey(SYSVAR_RENT_PUBKEY),
         programIds().ata,
+        tokens,
+        components.map((c) => c.amount),
         programIds().swap,
-        components[0].amount,
-        components[1].amount,
-        components[2].amount,
       )
     );
     console.log("_addLiquidityNewPool 8");
@@ -889,7 +943,7 @@ async function _addLiquidityNewPool(
       walletPubKey,
       transactions,
       signTransaction,
-      [poolAccount, vaultKeyPair, ...signers]
+      [poolAccount, ...vaultKeyPairs, ...signers]
     );
 
     if (txId == null) {
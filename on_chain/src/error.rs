@@ -1,3 +1,6 @@
+// The FromPrimitive derive expands to an impl inside a const block
+#![allow(non_local_definitions)]
+
 use num_derive::FromPrimitive as DeriveFromPrimitive;
 use num_traits::FromPrimitive;
 use solana_program::{
@@ -33,6 +36,52 @@ pub enum AppError {
   InvalidMint,
   #[error("Exceed limit")]
   ExceedLimit,
+  #[error("Invalid multisig")]
+  InvalidMultisig,
+  #[error("Operation paused")]
+  PausedOperation,
+  #[error("Invalid pause flags")]
+  InvalidPauseFlags,
+  #[error("Pool inactive")]
+  InactivePool,
+  #[error("Invalid state transition")]
+  InvalidStateTransition,
+  #[error("Reserve not empty")]
+  NonEmptyReserve,
+  #[error("Unsupported mint extension")]
+  UnsupportedMintExtension,
+  #[error("Allowlist is full")]
+  FullAllowlist,
+  #[error("Trader already allowlisted")]
+  DuplicatedTrader,
+  #[error("Trader not allowlisted")]
+  UnknownTrader,
+  #[error("Invalid fee tiers")]
+  InvalidFeeTiers,
+  #[error("Invalid fee bounds")]
+  InvalidFeeBounds,
+  #[error("Price deviation exceeded")]
+  PriceDeviation,
+  #[error("Trade too large")]
+  TradeTooLarge,
+  #[error("Sandwich detected")]
+  SandwichDetected,
+  #[error("Router registry is full")]
+  FullRegistry,
+  #[error("Router not registered")]
+  UnknownRouter,
+  #[error("Router already registered")]
+  DuplicatedRouter,
+  #[error("Rate expired")]
+  ExpiredRate,
+  #[error("Exceed reserve")]
+  ExceedReserve,
+  #[error("Referrer can't be the trader")]
+  SelfReferral,
+  #[error("A pool token is retiring")]
+  RetiringToken,
+  #[error("Reserve below the minimum")]
+  BelowMinimumReserve,
 }
 
 impl From<AppError> for ProgramError {
@@ -64,6 +113,29 @@ impl PrintProgramError for AppError {
       AppError::InsufficientFunds => msg!("Error: Insufficient funds"),
       AppError::InvalidMint => msg!("Error: Invalid mint"),
       AppError::ExceedLimit => msg!("Error: Exceed limit"),
+      AppError::InvalidMultisig => msg!("Error: Invalid multisig"),
+      AppError::PausedOperation => msg!("Error: Operation paused"),
+      AppError::InvalidPauseFlags => msg!("Error: Invalid pause flags"),
+      AppError::InactivePool => msg!("Error: Pool inactive"),
+      AppError::InvalidStateTransition => msg!("Error: Invalid state transition"),
+      AppError::NonEmptyReserve => msg!("Error: Reserve not empty"),
+      AppError::UnsupportedMintExtension => msg!("Error: Unsupported mint extension"),
+      AppError::FullAllowlist => msg!("Error: Allowlist is full"),
+      AppError::DuplicatedTrader => msg!("Error: Trader already allowlisted"),
+      AppError::UnknownTrader => msg!("Error: Trader not allowlisted"),
+      AppError::InvalidFeeTiers => msg!("Error: Invalid fee tiers"),
+      AppError::InvalidFeeBounds => msg!("Error: Invalid fee bounds"),
+      AppError::PriceDeviation => msg!("Error: Price deviation exceeded"),
+      AppError::TradeTooLarge => msg!("Error: Trade too large"),
+      AppError::SandwichDetected => msg!("Error: Sandwich detected"),
+      AppError::FullRegistry => msg!("Error: Router registry is full"),
+      AppError::UnknownRouter => msg!("Error: Router not registered"),
+      AppError::DuplicatedRouter => msg!("Error: Router already registered"),
+      AppError::ExpiredRate => msg!("Error: Rate expired"),
+      AppError::ExceedReserve => msg!("Error: Exceed reserve"),
+      AppError::SelfReferral => msg!("Error: Referrer can't be the trader"),
+      AppError::RetiringToken => msg!("Error: A pool token is retiring"),
+      AppError::BelowMinimumReserve => msg!("Error: Reserve below the minimum"),
     }
   }
 }
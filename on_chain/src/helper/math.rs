@@ -9,7 +9,7 @@ pub trait Roots {
         return self;
       }
   
-      let bits = (128 - self.leading_zeros() + 1) / 2;
+      let bits = (128 - self.leading_zeros()).div_ceil(2);
       let mut start = 1 << (bits - 1);
       let mut end = 1 << (bits + 1);
       while start < end {
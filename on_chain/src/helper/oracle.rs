@@ -1,16 +1,20 @@
-use crate::helper::math::Roots;
+use crate::schema::pool::Curve;
 
 const TRIPPLE_PRECISION: u128 = 1000000000000000000;
-const FEE: u64 = 2500000;
-const EARNING: u64 = 500000;
+pub const FEE: u64 = 2500000;
+pub const EARNING: u64 = 500000;
 const DECIMALS: u64 = 1000000000; 
+// The volatility keeps 99% of itself per slot
+const VOLATILITY_DECAY: u64 = 990000000;
+const VOLATILITY_FEE_FACTOR: u64 = 250000000;
+const IMBALANCE_FEE_FACTOR: u64 = 10000000;
 
 pub struct Oracle {}
 
 impl Oracle {
   //new_ask_t/ask = bid/new_bid -> new_ask_t = bid * ask / new_bid
-  //fee = (ask - new_ask_t) * 0.25
-  //earning = (ask - new_ask_t) * 0.05
+  //fee = (ask - new_ask_t) * fee_rate, 0.25 by default
+  //earning = (ask - new_ask_t) * earning_rate, 0.05 by default
   //new_ask = new_ask_t + fee
   //paid_amount = ask - new_ask_t - fee - earning
   pub fn curve(new_bid_reserve: u64, bid_reserve: u64, ask_reserve: u64) -> Option<u64> {
@@ -27,24 +31,47 @@ impl Oracle {
     Some(new_ask_reserve)
   }
 
+  //new_ask = ask - (new_bid - bid), 1:1 without slippage and never draining the ask reserve
+  pub fn curve_sum(new_bid_reserve: u64, bid_reserve: u64, ask_reserve: u64) -> Option<u64> {
+    let delta = new_bid_reserve.checked_sub(bid_reserve)?;
+    let new_ask_reserve = ask_reserve.checked_sub(delta)?;
+    if new_ask_reserve == 0 {
+      return None;
+    }
+
+    Some(new_ask_reserve)
+  }
+
+  pub fn curve_by(
+    curve: Curve,
+    new_bid_reserve: u64,
+    bid_reserve: u64,
+    ask_reserve: u64,
+  ) -> Option<u64> {
+    match curve {
+      Curve::Product => Self::curve(new_bid_reserve, bid_reserve, ask_reserve),
+      Curve::ConstantSum => Self::curve_sum(new_bid_reserve, bid_reserve, ask_reserve),
+    }
+  }
+
   pub fn curve_in_fee(
+    curve: Curve,
     new_bid_reserve: u64,
     bid_reserve: u64,
     ask_reserve: u64,
-    is_exempted: bool,
+    fee_rate: u64,
+    earning_rate: u64,
   ) -> Option<(u64, u64, u64)> {
-    let new_ask_reserve_without_fee = Self::curve(new_bid_reserve, bid_reserve, ask_reserve)?;
+    let new_ask_reserve_without_fee =
+      Self::curve_by(curve, new_bid_reserve, bid_reserve, ask_reserve)?;
     let paid_amount_without_fee = ask_reserve.checked_sub(new_ask_reserve_without_fee)?;
 
     let fee = (paid_amount_without_fee as u128)
-      .checked_mul(FEE as u128)?
+      .checked_mul(fee_rate as u128)?
+      .checked_div(DECIMALS as u128)? as u64;
+    let earning = (paid_amount_without_fee as u128)
+      .checked_mul(earning_rate as u128)?
       .checked_div(DECIMALS as u128)? as u64;
-    let mut earning: u64 = 0;
-    if !is_exempted {
-      earning = (paid_amount_without_fee as u128)
-        .checked_mul(EARNING as u128)?
-        .checked_div(DECIMALS as u128)? as u64;
-    }
 
     let paid_amount = paid_amount_without_fee
       .checked_sub(fee)?
@@ -53,79 +80,534 @@ impl Oracle {
     Some((new_ask_reserve, paid_amount, earning))
   }
 
-  pub fn _rake(
-    delta: u64,
-    reserves: Vec<u64>,
+  //discounted_rate = rate * (1 - discount)
+  pub fn discounted(rate: u64, discount: u64) -> Option<u64> {
+    let discounted_rate = (rate as u128)
+      .checked_mul((DECIMALS as u128).checked_sub(discount as u128)?)?
+      .checked_div(DECIMALS as u128)? as u64;
+    Some(discounted_rate)
+  }
+
+  //value = scaled_amount * scaled_reserve_0 / scaled_reserve at the spot price of the product curve,
+  //and scaled_amount 1:1 on the constant sum, in the underlying of token 0
+  pub fn value_in_reserve_0(
+    curve: Curve,
+    scaled_amount: u64,
+    scaled_reserve: u64,
+    scaled_reserve_0: u64,
+  ) -> Option<u64> {
+    if scaled_reserve == 0 {
+      return None;
+    }
+    match curve {
+      Curve::Product => {
+        let value = (scaled_amount as u128)
+          .checked_mul(scaled_reserve_0 as u128)?
+          .checked_div(scaled_reserve as u128)?;
+        u64::try_from(value).ok()
+      }
+      Curve::ConstantSum => Some(scaled_amount),
+    }
+  }
+
+  //scaled = amount * rate, the amount in the underlying
+  pub fn scale(amount: u64, rate: u64) -> Option<u64> {
+    let scaled = (amount as u128)
+      .checked_mul(rate as u128)?
+      .checked_div(DECIMALS as u128)?;
+    u64::try_from(scaled).ok()
+  }
+
+  //amount = scaled / rate
+  pub fn unscale(scaled: u64, rate: u64) -> Option<u64> {
+    if rate == 0 {
+      return None;
+    }
+    let amount = (scaled as u128)
+      .checked_mul(DECIMALS as u128)?
+      .checked_div(rate as u128)?;
+    u64::try_from(amount).ok()
+  }
+
+  //scaled = ceil(amount * rate)
+  pub fn scale_up(amount: u64, rate: u64) -> Option<u64> {
+    let scaled = (amount as u128)
+      .checked_mul(rate as u128)?
+      .checked_add(DECIMALS as u128 - 1)?
+      .checked_div(DECIMALS as u128)?;
+    u64::try_from(scaled).ok()
+  }
+
+  //amount = ceil(scaled / rate)
+  pub fn unscale_up(scaled: u64, rate: u64) -> Option<u64> {
+    if rate == 0 {
+      return None;
+    }
+    let amount = (scaled as u128)
+      .checked_mul(DECIMALS as u128)?
+      .checked_add(rate as u128 - 1)?
+      .checked_div(rate as u128)?;
+    u64::try_from(amount).ok()
+  }
+
+  //the curve runs on the reserves scaled into the underlying, then paid_amount and earning are scaled back
+  //new_ask = ask - paid_amount - earning
+  #[allow(clippy::too_many_arguments)]
+  pub fn curve_in_fee_with_rates(
+    curve: Curve,
+    new_bid_reserve: u64,
+    bid_reserve: u64,
+    ask_reserve: u64,
+    bid_rate: u64,
+    ask_rate: u64,
+    fee_rate: u64,
+    earning_rate: u64,
+  ) -> Option<(u64, u64, u64)> {
+    let (_, scaled_paid_amount, scaled_earning) = Self::curve_in_fee(
+      curve,
+      Self::scale(new_bid_reserve, bid_rate)?,
+      Self::scale(bid_reserve, bid_rate)?,
+      Self::scale(ask_reserve, ask_rate)?,
+      fee_rate,
+      earning_rate,
+    )?;
+    let paid_amount = Self::unscale(scaled_paid_amount, ask_rate)?;
+    let earning = Self::unscale(scaled_earning, ask_rate)?;
+    let new_ask_reserve = ask_reserve.checked_sub(paid_amount)?.checked_sub(earning)?;
+    Some((new_ask_reserve, paid_amount, earning))
+  }
+
+  //1 token 1 = price * 10^expo token 0 in whole tokens
+  //= price * 10^(expo + decimals_0 - decimals_1) in the smallest units
+  pub fn peg_expo(expo: i32, decimals_0: u8, decimals_1: u8) -> Option<i32> {
+    expo
+      .checked_add(decimals_0 as i32)?
+      .checked_sub(decimals_1 as i32)
+  }
+
+  //quote = bid_amount * price * 10^expo when selling token 1, bid_amount / (price * 10^expo) otherwise
+  //spread = quote * spread_rate, stays in the pool like the fee
+  //earning = quote * earning_rate
+  //paid_amount = quote - spread - earning
+  //new_ask = ask - paid_amount - earning
+  pub fn peg_in_fee(
+    bid_amount: u64,
+    ask_reserve: u64,
+    price: u64,
+    expo: i32,
+    is_bid_token_1: bool,
+    spread_rate: u64,
+    earning_rate: u64,
   ) -> Option<(u64, u64, u64)> {
-    for reserve in reserves {
-      if reserve == 0 {
+    if price == 0 {
+      return None;
+    }
+    let scale = 10u128.checked_pow(expo.unsigned_abs())?;
+    let (numerator, denominator) = match (is_bid_token_1, expo >= 0) {
+      (true, true) => ((price as u128).checked_mul(scale)?, 1),
+      (true, false) => (price as u128, scale),
+      (false, true) => (1, (price as u128).checked_mul(scale)?),
+      (false, false) => (scale, price as u128),
+    };
+    let quote = (bid_amount as u128)
+      .checked_mul(numerator)?
+      .checked_div(denominator)?;
+    if quote > ask_reserve as u128 {
+      return None;
+    }
+    let quote = quote as u64;
+
+    let spread = (quote as u128)
+      .checked_mul(spread_rate as u128)?
+      .checked_div(DECIMALS as u128)? as u64;
+    let earning = (quote as u128)
+      .checked_mul(earning_rate as u128)?
+      .checked_div(DECIMALS as u128)? as u64;
+    let paid_amount = quote.checked_sub(spread)?.checked_sub(earning)?;
+    let new_ask_reserve = ask_reserve.checked_sub(paid_amount)?.checked_sub(earning)?;
+    Some((new_ask_reserve, paid_amount, earning))
+  }
+
+  //referral = earning * referral_share
+  pub fn split_earning(earning: u64, referral_share: u64) -> Option<(u64, u64)> {
+    let referral = (earning as u128)
+      .checked_mul(referral_share as u128)?
+      .checked_div(DECIMALS as u128)? as u64;
+    Some((earning.checked_sub(referral)?, referral))
+  }
+
+  //rate = bps / 10000
+  pub fn bps_to_rate(bps: u16) -> u64 {
+    (bps as u64) * DECIMALS / 10000
+  }
+
+  //portion = amount * bps / 10000
+  pub fn bps_of(amount: u64, bps: u16) -> Option<u64> {
+    let portion = (amount as u128)
+      .checked_mul(bps as u128)?
+      .checked_div(10000)? as u64;
+    Some(portion)
+  }
+
+  //volatility = volatility * decay ^ elapsed_slots
+  pub fn decay_volatility(volatility: u64, elapsed_slots: u64) -> Option<u64> {
+    let mut factor = DECIMALS as u128;
+    let mut base = VOLATILITY_DECAY as u128;
+    let mut exp = elapsed_slots;
+    while exp > 0 && factor > 0 {
+      if exp & 1 == 1 {
+        factor = factor.checked_mul(base)?.checked_div(DECIMALS as u128)?;
+      }
+      base = base.checked_mul(base)?.checked_div(DECIMALS as u128)?;
+      exp >>= 1;
+    }
+    let decayed_volatility = (volatility as u128)
+      .checked_mul(factor)?
+      .checked_div(DECIMALS as u128)? as u64;
+    Some(decayed_volatility)
+  }
+
+  //price = ask / bid
+  //price_change = |new_price / price - 1| = |new_ask * bid / (new_bid * ask) - 1|
+  pub fn price_change(
+    new_bid_reserve: u64,
+    new_ask_reserve: u64,
+    bid_reserve: u64,
+    ask_reserve: u64,
+  ) -> Option<u64> {
+    let numerator = (new_ask_reserve as u128).checked_mul(bid_reserve as u128)?;
+    let denominator = (new_bid_reserve as u128).checked_mul(ask_reserve as u128)?;
+    if denominator == 0 {
+      return None;
+    }
+    let difference = numerator.abs_diff(denominator);
+    let change = difference
+      .checked_mul(DECIMALS as u128)?
+      .checked_div(denominator)?;
+    Some(change.min(u64::MAX as u128) as u64)
+  }
+
+  //spot_change = the largest price_change of a token in token 0, legs without a last reserve skipped.
+  //The rates are taken as unchanged over a slot, so they cancel out of the change
+  pub fn spot_change(reserves: &[u64], last_reserves: &[u64]) -> Option<u64> {
+    let mut spot_change = 0;
+    for i in 1..reserves.len().min(last_reserves.len()) {
+      if last_reserves[0] == 0 || last_reserves[i] == 0 || reserves[i] == 0 {
+        continue;
+      }
+      let change = Self::price_change(reserves[i], reserves[0], last_reserves[i], last_reserves[0])?;
+      spot_change = spot_change.max(change);
+    }
+    Some(spot_change)
+  }
+
+  //imbalance = |bid - ask| / (bid + ask)
+  pub fn imbalance(bid_reserve: u64, ask_reserve: u64) -> Option<u64> {
+    let total = (bid_reserve as u128).checked_add(ask_reserve as u128)?;
+    if total == 0 {
+      return None;
+    }
+    let difference = (bid_reserve as i128 - ask_reserve as i128).unsigned_abs();
+    let imbalance = difference
+      .checked_mul(DECIMALS as u128)?
+      .checked_div(total)? as u64;
+    Some(imbalance)
+  }
+
+  //fee_rate = FEE + volatility * 0.25 + imbalance_increase * 0.01, within [min_fee, max_fee]
+  pub fn dynamic_fee(
+    volatility: u64,
+    imbalance_increase: u64,
+    min_fee: u64,
+    max_fee: u64,
+  ) -> Option<u64> {
+    let volatility_fee = (volatility as u128)
+      .checked_mul(VOLATILITY_FEE_FACTOR as u128)?
+      .checked_div(DECIMALS as u128)?;
+    let imbalance_fee = (imbalance_increase as u128)
+      .checked_mul(IMBALANCE_FEE_FACTOR as u128)?
+      .checked_div(DECIMALS as u128)?;
+    let fee_rate = (FEE as u128)
+      .checked_add(volatility_fee)?
+      .checked_add(imbalance_fee)?
+      .min(max_fee as u128)
+      .max(min_fee as u128);
+    Some(fee_rate as u64)
+  }
+
+  // Fixed-point power, x and the result are scaled by TRIPPLE_PRECISION
+  fn pow_fixed(x: u128, n: usize) -> Option<u128> {
+    let mut result = TRIPPLE_PRECISION;
+    for _ in 0..n {
+      result = result.checked_mul(x)?.checked_div(TRIPPLE_PRECISION)?;
+    }
+    Some(result)
+  }
+
+  //ratio = (new_reserve_1/reserve_1 * ... * new_reserve_n/reserve_n)^(1/n)
+  //new_reserve_i <= reserve_i, the ratio is scaled by TRIPPLE_PRECISION and rounded down
+  pub fn invariant_ratio(new_reserves: &[u64], reserves: &[u64]) -> Option<u128> {
+    let n = reserves.len();
+    if n == 0 || n != new_reserves.len() {
+      return None;
+    }
+    let mut product = TRIPPLE_PRECISION;
+    for i in 0..n {
+      if reserves[i] == 0 || new_reserves[i] > reserves[i] {
         return None;
       }
+      let ratio = (new_reserves[i] as u128)
+        .checked_mul(TRIPPLE_PRECISION)?
+        .checked_div(reserves[i] as u128)?;
+      product = product.checked_mul(ratio)?.checked_div(TRIPPLE_PRECISION)?;
     }
-    
-    if delta == 0 {
-      return Some((0, 0, 0));
-    }
-    let cbrt_of_delta_plus_reserve = (delta as u128)
-      .checked_add(reserve_s as u128)?
-      .checked_mul(TRIPPLE_PRECISION)?
-      .cbrt(); 
-    let cbrt_of_reserve = (reserve_s as u128).checked_mul(TRIPPLE_PRECISION)?.cbrt(); 
-    let z = cbrt_of_delta_plus_reserve
-      .pow(2)
-      .checked_mul(cbrt_of_reserve)?
-      .checked_div(TRIPPLE_PRECISION)?
-      .checked_sub(reserve_s as u128)?;
-    let x = z
-      .checked_add(reserve_s as u128)?
-      .checked_mul(reserve_s as u128)?
-      .sqrt()
-      .checked_sub(reserve_s as u128)?;
-    let y = z.checked_sub(x)?;
-    let s = (delta as u128).checked_sub(z)? as u64;
-    let a = (reserve_a as u128)
-      .checked_mul(x)?
-      .checked_div((reserve_s as u128).checked_add(x)?)? as u64;
-    let b = (reserve_b as u128)
-      .checked_mul(y)?
-      .checked_div((reserve_s as u128).checked_add(z)?)? as u64;
-    Some((s, a, b))
+    Some(Self::root_fixed(product, n, TRIPPLE_PRECISION))
   }
 
+  //ratio = (new_reserve_1/reserve_1 * ... * new_reserve_n/reserve_n)^(1/n)
+  //new_reserve_i may be on either side of reserve_i, the ratio is scaled by TRIPPLE_PRECISION and rounded down
+  pub fn growth_ratio(new_reserves: &[u64], reserves: &[u64]) -> Option<u128> {
+    let n = reserves.len();
+    if n == 0 || n != new_reserves.len() {
+      return None;
+    }
+    let mut product = TRIPPLE_PRECISION;
+    for i in 0..n {
+      if reserves[i] == 0 {
+        return None;
+      }
+      let ratio = (new_reserves[i] as u128)
+        .checked_mul(TRIPPLE_PRECISION)?
+        .checked_div(reserves[i] as u128)?;
+      product = product.checked_mul(ratio)?.checked_div(TRIPPLE_PRECISION)?;
+    }
+    Some(Self::root_fixed(product, n, product.max(TRIPPLE_PRECISION)))
+  }
+
+  // Fixed-point n-th root rounded down, searched in [0, high]
+  fn root_fixed(product: u128, n: usize, high: u128) -> u128 {
+    let mut low: u128 = 0;
+    let mut high = high;
+    while low < high {
+      let mid = low + (high - low).div_ceil(2);
+      match Self::pow_fixed(mid, n) {
+        Some(power) if power <= product => low = mid,
+        _ => high = mid - 1,
+      }
+    }
+    low
+  }
+
+  //ratio = invariant growth of new_reserve_i = reserve_i + delta_i
+  //ideal_i = reserve_i * ratio (proportional deposit of the same invariant)
+  //fee_i = |new_reserve_i - ideal_i| * 0.25
+  //lpt = floor(reserve_lpt * (ratio of new_reserve_i - fee_i - 1))
   pub fn rake(
     deltas: Vec<u64>,
     reserves: Vec<u64>,
     reserve_lpt: u64,
   ) -> Option<(u64, Vec<u64>)> {
-    let rs = reserves;
-    let ra = reserve_a;
-    let rb = reserve_b;
-    let rlpt = reserve_lpt;
-
-    let (s1, _a1, _b1) = Self::_rake(delta_s, rs, ra, rb)?;
-    let rs = rs.checked_add(delta_s)?;
-    let rs_prime = rs.checked_sub(s1)?;
-    let lpt1 = (s1 as u128)
-      .checked_mul(rlpt as u128)?
-      .checked_div(rs_prime as u128)? as u64;
-    let rlpt = rlpt.checked_add(lpt1)?;
-
-    let (_a2, _b2, s2) = Self::_rake(delta_a, ra, rb, rs)?;
-    let ra = ra.checked_add(delta_a)?;
-    let rs_prime = rs.checked_sub(s2)?;
-    let lpt2 = (s2 as u128)
-      .checked_mul(rlpt as u128)?
-      .checked_div(rs_prime as u128)? as u64;
-    let rlpt = rlpt.checked_add(lpt2)?;
-
-    let (_b3, s3, _a3) = Self::_rake(delta_b, rb, rs, ra)?;
-    let rb = rb.checked_add(delta_b)?;
-    let rs_prime = rs.checked_sub(s3)?;
-    let lpt3 = (s3 as u128)
-      .checked_mul(rlpt as u128)?
-      .checked_div(rs_prime as u128)? as u64;
-    let lpt = lpt1.checked_add(lpt2)?.checked_add(lpt3)?;
-
-    Some((lpt, rs, ra, rb))
+    let n = reserves.len();
+    if n != deltas.len() {
+      return None;
+    }
+    let mut new_reserves = Vec::with_capacity(n);
+    for i in 0..n {
+      new_reserves.push(reserves[i].checked_add(deltas[i])?);
+    }
+    let ratio = Self::growth_ratio(&new_reserves, &reserves)?;
+
+    let mut charged_reserves = Vec::with_capacity(n);
+    for i in 0..n {
+      let ideal = (reserves[i] as u128)
+        .checked_mul(ratio)?
+        .checked_div(TRIPPLE_PRECISION)?;
+      let imbalance = (new_reserves[i] as u128).abs_diff(ideal);
+      let fee = imbalance
+        .checked_mul(FEE as u128)?
+        .checked_div(DECIMALS as u128)? as u64;
+      charged_reserves.push(new_reserves[i].saturating_sub(fee));
+    }
+    let charged_ratio = Self::growth_ratio(&charged_reserves, &reserves)?;
+
+    let lpt = (reserve_lpt as u128)
+      .checked_mul(charged_ratio.saturating_sub(TRIPPLE_PRECISION))?
+      .checked_div(TRIPPLE_PRECISION)?;
+    Some((u64::try_from(lpt).ok()?, new_reserves))
+  }
+
+  //ideal_i = reserve_i * ratio (proportional withdrawal of the same invariant)
+  //fee_i = |ideal_i - new_reserve_i| * 0.25
+  //lpt = ceil(reserve_lpt * (1 - ratio of new_reserve_i - fee_i))
+  pub fn unrake(
+    amounts: Vec<u64>,
+    reserves: Vec<u64>,
+    reserve_lpt: u64,
+  ) -> Option<(u64, Vec<u64>)> {
+    let n = reserves.len();
+    if n != amounts.len() {
+      return None;
+    }
+    let mut new_reserves = Vec::with_capacity(n);
+    for i in 0..n {
+      new_reserves.push(reserves[i].checked_sub(amounts[i])?);
+    }
+    let ratio = Self::invariant_ratio(&new_reserves, &reserves)?;
+
+    let mut charged_reserves = Vec::with_capacity(n);
+    for i in 0..n {
+      let ideal = (reserves[i] as u128)
+        .checked_mul(ratio)?
+        .checked_div(TRIPPLE_PRECISION)? as u64;
+      let imbalance = ideal.abs_diff(new_reserves[i]);
+      let fee = (imbalance as u128)
+        .checked_mul(FEE as u128)?
+        .checked_div(DECIMALS as u128)? as u64;
+      charged_reserves.push(new_reserves[i].checked_sub(fee)?);
+    }
+    let charged_ratio = Self::invariant_ratio(&charged_reserves, &reserves)?;
+
+    let lpt = (reserve_lpt as u128)
+      .checked_mul(TRIPPLE_PRECISION.checked_sub(charged_ratio)?)?
+      .checked_add(TRIPPLE_PRECISION - 1)?
+      .checked_div(TRIPPLE_PRECISION)? as u64;
+    Some((lpt, new_reserves))
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_peg_in_fee() {
+    // 1 token 1 = 2.5 token 0, with the price 250 at expo -2
+    let spread = Oracle::bps_to_rate(10);
+    // Selling token 1: quote = 1000 * 2.5 = 2500, spread = 2, earning = 1, rounded down
+    assert_eq!(
+      Oracle::peg_in_fee(1000, 1_000_000, 250, -2, true, spread, EARNING),
+      Some((1_000_000 - 2497 - 1, 2497, 1))
+    );
+    // Selling token 0: quote = 2500 / 2.5 = 1000
+    assert_eq!(
+      Oracle::peg_in_fee(2500, 1_000_000, 250, -2, false, 0, 0),
+      Some((1_000_000 - 1000, 1000, 0))
+    );
+    // A positive exponent multiplies, 1 token 1 = 2 * 10^3 token 0
+    assert_eq!(
+      Oracle::peg_in_fee(3, 1_000_000, 2, 3, true, 0, 0),
+      Some((1_000_000 - 6000, 6000, 0))
+    );
+    // The quote can't take more than the ask reserve
+    assert_eq!(Oracle::peg_in_fee(1000, 2499, 250, -2, true, 0, 0), None);
+    // 1000 units of a 6 decimals token 1 are 0.001 token 1, i.e. 0.0025 of a 9 decimals token 0
+    let expo = Oracle::peg_expo(-2, 9, 6).unwrap();
+    assert_eq!(expo, 1);
+    assert_eq!(
+      Oracle::peg_in_fee(1000, 10_000_000, 250, expo, true, 0, 0),
+      Some((10_000_000 - 2_500_000, 2_500_000, 0))
+    );
+    assert_eq!(
+      Oracle::peg_in_fee(2_500_000, 1_000_000, 250, expo, false, 0, 0),
+      Some((1_000_000 - 1000, 1000, 0))
+    );
+    assert_eq!(Oracle::peg_in_fee(1000, 1_000_000, 0, -2, true, 0, 0), None);
+  }
+
+  #[test]
+  fn test_curve_sum() {
+    assert_eq!(Oracle::curve_sum(1100, 1000, 1000), Some(900));
+    // Swaps can't drain the ask reserve nor take the bid reserve down
+    assert_eq!(Oracle::curve_sum(2000, 1000, 1000), None);
+    assert_eq!(Oracle::curve_sum(3000, 1000, 1000), None);
+    assert_eq!(Oracle::curve_sum(900, 1000, 1000), None);
+  }
+
+  #[test]
+  fn test_scale() {
+    let rate = 1_500_000_000; // 1.5
+    assert_eq!(Oracle::scale(3, rate), Some(4));
+    assert_eq!(Oracle::scale_up(3, rate), Some(5));
+    assert_eq!(Oracle::unscale(5, rate), Some(3));
+    assert_eq!(Oracle::unscale_up(5, rate), Some(4));
+    assert_eq!(Oracle::unscale_up(6, rate), Some(4));
+    assert_eq!(Oracle::unscale(5, 0), None);
+    assert_eq!(Oracle::unscale_up(5, 0), None);
+  }
+
+  #[test]
+  fn test_dynamic_fee() {
+    assert_eq!(Oracle::dynamic_fee(0, 0, 0, u64::MAX), Some(FEE));
+    // 1% volatility adds 0.25%, a 10% imbalance increase adds 0.1%
+    assert_eq!(
+      Oracle::dynamic_fee(10_000_000, 100_000_000, 0, u64::MAX),
+      Some(FEE + 2_500_000 + 1_000_000)
+    );
+    assert_eq!(Oracle::dynamic_fee(DECIMALS, 0, 0, 10_000_000), Some(10_000_000));
+    assert_eq!(Oracle::dynamic_fee(0, 0, 3_000_000, u64::MAX), Some(3_000_000));
+  }
+
+  #[test]
+  fn test_spot_change() {
+    // Token 1 went from 1 to 1.1 token 0, token 2 didn't move
+    assert_eq!(
+      Oracle::spot_change(&[1100, 1000, 500], &[1000, 1000, 500]),
+      Some(100_000_000)
+    );
+    // Token 2 fell to half of its price meanwhile, the larger move counts
+    assert_eq!(
+      Oracle::spot_change(&[1100, 1000, 1100], &[1000, 1000, 500]),
+      Some(500_000_000)
+    );
+    // A new leg has no last reserve yet
+    assert_eq!(Oracle::spot_change(&[1000, 1000, 500], &[1000, 1000, 0]), Some(0));
+  }
+
+  #[test]
+  fn test_value_in_reserve_0() {
+    assert_eq!(Oracle::value_in_reserve_0(Curve::Product, 100, 1000, 4000), Some(400));
+    assert_eq!(Oracle::value_in_reserve_0(Curve::ConstantSum, 100, 1000, 4000), Some(100));
+    assert_eq!(Oracle::value_in_reserve_0(Curve::Product, 100, 0, 4000), None);
+  }
+
+  #[test]
+  fn test_rake() {
+    // A proportional deposit mints its share of the supply and no fee
+    assert_eq!(
+      Oracle::rake(vec![100, 100], vec![1000, 1000], 1000),
+      Some((100, vec![1100, 1100]))
+    );
+    // A single-sided one pays the fee on its imbalance,
+    // floor(1_000_000 * (sqrt(1.1) - 1)) without the fee
+    let (lpt, new_reserves) =
+      Oracle::rake(vec![100_000, 0], vec![1_000_000, 1_000_000], 1_000_000).unwrap();
+    assert_eq!(new_reserves, vec![1_100_000, 1_000_000]);
+    assert!(lpt < 48_808);
+    assert!(lpt > 48_000);
+    // Nothing deposited mints nothing, and an empty reserve can't be priced
+    assert_eq!(
+      Oracle::rake(vec![0, 0, 0], vec![1000, 1000, 1000], 1000),
+      Some((0, vec![1000, 1000, 1000]))
+    );
+    assert_eq!(Oracle::rake(vec![100, 100], vec![0, 1000], 1000), None);
+    assert_eq!(Oracle::rake(vec![100], vec![1000, 1000], 1000), None);
+  }
+
+  #[test]
+  fn test_unrake() {
+    // A proportional withdrawal costs its share of the supply and no fee
+    assert_eq!(
+      Oracle::unrake(vec![100, 100], vec![1000, 1000], 1000),
+      Some((100, vec![900, 900]))
+    );
+    // An imbalanced one pays the fee on top of the invariant it takes,
+    // ceil(1_000_000 * (1 - sqrt(0.9))) without the fee
+    let (lpt, new_reserves) =
+      Oracle::unrake(vec![100_000, 0], vec![1_000_000, 1_000_000], 1_000_000).unwrap();
+    assert_eq!(new_reserves, vec![900_000, 1_000_000]);
+    assert!(lpt > 51_317);
+    // Withdrawals can't exceed the reserves and must name every token
+    assert_eq!(Oracle::unrake(vec![1001, 0], vec![1000, 1000], 1000), None);
+    assert_eq!(Oracle::unrake(vec![100], vec![1000, 1000], 1000), None);
   }
 }
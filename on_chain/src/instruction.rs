@@ -1,7 +1,15 @@
 use crate::error::AppError;
+use crate::schema::pool::{Curve, FeeTier, HolderTier};
+use num_enum::TryFromPrimitive;
 use solana_program::program_error::ProgramError;
 use std::convert::TryInto;
 
+// Swap tag and the positions of the Swap accounts that other swaps are matched on
+pub const SWAP_TAG: u8 = 3;
+pub const SWAP_POOL_INDEX: usize = 1;
+pub const SWAP_TREASURY_BID_INDEX: usize = 5;
+pub const SWAP_TREASURY_ASK_INDEX: usize = 8;
+
 #[derive(Clone, Debug, PartialEq)]
 pub enum AppInstruction {
   InitializePool {
@@ -9,9 +17,11 @@ pub enum AppInstruction {
   },
   AddLiquidity {
     deltas: Vec<u64>,
+    min_lpt: u64,
   },
   RemoveLiquidity {
     lpt: u64,
+    min_amounts: Vec<u64>,
   },
   Swap {
     amount: u64,
@@ -23,6 +33,95 @@ pub enum AppInstruction {
     amount: u64,
   },
   TransferPoolOwnership,
+  InitializeMultisig {
+    m: u8,
+  },
+  TogglePause {
+    flags: u8,
+  },
+  UpdatePoolState {
+    state: u8,
+  },
+  AddPoolToken {
+    amount: u64,
+    value: u64, // the amount quoted in token 0
+    min_lpt: u64,
+  },
+  RemovePoolToken,
+  ClosePool,
+  AddLiquidityBalanced {
+    lpt_out: u64,
+    max_amounts: Vec<u64>,
+  },
+  RemoveLiquidityImbalanced {
+    amounts: Vec<u64>,
+    max_lpt: u64,
+  },
+  InitializeAllowlist {
+    earning: u64,
+  },
+  AddAllowlistTrader,
+  RemoveAllowlistTrader,
+  SetAllowlistEarning {
+    earning: u64,
+  },
+  SetReferralShare {
+    share: u64,
+  },
+  SetFeeTiers {
+    fee_tiers: Vec<FeeTier>,
+  },
+  InitializeTraderVolume,
+  SetHolderTiers {
+    holder_tiers: Vec<HolderTier>,
+  },
+  SetDynamicFee {
+    is_enabled: bool,
+    min_fee: u64,
+    max_fee: u64,
+  },
+  SetCircuitBreaker {
+    max_deviation: u16,
+  },
+  SetTradeLimits {
+    max_bid_ratio: u16,
+    max_ask_ratio: u16,
+  },
+  SetAntiSandwich {
+    is_enabled: bool,
+  },
+  InitializeRouterRegistry,
+  AddRouter,
+  RemoveRouter,
+  SetPricePeg {
+    max_staleness: u64,
+    spread: u16,
+    max_confidence: u16,
+  },
+  InitializeRateProvider {
+    rate: u64,
+  },
+  UpdateRate {
+    rate: u64,
+  },
+  SetPoolRateProvider {
+    rate_duration: i64,
+  },
+  RefreshRates,
+  SetCurve {
+    curve: Curve,
+  },
+  SetEarning {
+    is_enabled: bool,
+  },
+  SetMinimumReserve {
+    minimum: u64,
+  },
+  RetirePoolToken,
+  ExitPoolToken {
+    amount: u64,
+    max_lpt: u64,
+  },
 }
 
 impl AppInstruction {
@@ -32,11 +131,11 @@ impl AppInstruction {
       .ok_or(AppError::InvalidInstruction)?;
     Ok(match tag {
       0 => {
-        let restSize = rest.len();
+        let rest_size = rest.len();
         let mut offset = 0;
         let mut reserves = Vec::new();
 
-        while (offset + 8) <= restSize {
+        while (offset + 8) <= rest_size {
             let reserve = rest
                 .get(offset..offset+8)
                 .and_then(|slice| slice.try_into().ok())
@@ -52,11 +151,16 @@ impl AppInstruction {
         }
       }
       1 => {
-        let restSize = rest.len();
-        let mut offset = 0;
+        let min_lpt = rest
+          .get(..8)
+          .and_then(|slice| slice.try_into().ok())
+          .map(u64::from_le_bytes)
+          .ok_or(AppError::InvalidInstruction)?;
+        let rest_size = rest.len();
+        let mut offset = 8;
         let mut deltas = Vec::new();
 
-        while (offset + 8) <= restSize {
+        while (offset + 8) <= rest_size {
             let delta = rest
                 .get(offset..offset+8)
                 .and_then(|slice| slice.try_into().ok())
@@ -69,6 +173,7 @@ impl AppInstruction {
 
         Self::AddLiquidity {
           deltas,
+          min_lpt,
         }
       }
       2 => {
@@ -77,9 +182,24 @@ impl AppInstruction {
           .and_then(|slice| slice.try_into().ok())
           .map(u64::from_le_bytes)
           .ok_or(AppError::InvalidInstruction)?;
-        Self::RemoveLiquidity { lpt }
+        let rest_size = rest.len();
+        let mut offset = 8;
+        let mut min_amounts = Vec::new();
+
+        while (offset + 8) <= rest_size {
+            let min_amount = rest
+                .get(offset..offset+8)
+                .and_then(|slice| slice.try_into().ok())
+                .map(u64::from_le_bytes)
+                .ok_or(AppError::InvalidInstruction)?;
+
+            min_amounts.push(min_amount);
+            offset += 8;
+        }
+
+        Self::RemoveLiquidity { lpt, min_amounts }
       }
-      3 => {
+      SWAP_TAG => {
         let amount = rest
           .get(..8)
           .and_then(|slice| slice.try_into().ok())
@@ -103,6 +223,305 @@ impl AppInstruction {
         Self::Earn { amount }
       }
       7 => Self::TransferPoolOwnership,
+      8 => {
+        let m = rest
+          .first()
+          .copied()
+          .ok_or(AppError::InvalidInstruction)?;
+        Self::InitializeMultisig { m }
+      }
+      9 => {
+        let flags = rest
+          .first()
+          .copied()
+          .ok_or(AppError::InvalidInstruction)?;
+        Self::TogglePause { flags }
+      }
+      10 => {
+        let state = rest
+          .first()
+          .copied()
+          .ok_or(AppError::InvalidInstruction)?;
+        Self::UpdatePoolState { state }
+      }
+      11 => {
+        let amount = rest
+          .get(..8)
+          .and_then(|slice| slice.try_into().ok())
+          .map(u64::from_le_bytes)
+          .ok_or(AppError::InvalidInstruction)?;
+        let value = rest
+          .get(8..16)
+          .and_then(|slice| slice.try_into().ok())
+          .map(u64::from_le_bytes)
+          .ok_or(AppError::InvalidInstruction)?;
+        let min_lpt = rest
+          .get(16..24)
+          .and_then(|slice| slice.try_into().ok())
+          .map(u64::from_le_bytes)
+          .ok_or(AppError::InvalidInstruction)?;
+        Self::AddPoolToken {
+          amount,
+          value,
+          min_lpt,
+        }
+      }
+      12 => Self::RemovePoolToken,
+      13 => Self::ClosePool,
+      14 => {
+        let lpt_out = rest
+          .get(..8)
+          .and_then(|slice| slice.try_into().ok())
+          .map(u64::from_le_bytes)
+          .ok_or(AppError::InvalidInstruction)?;
+        let rest_size = rest.len();
+        let mut offset = 8;
+        let mut max_amounts = Vec::new();
+
+        while (offset + 8) <= rest_size {
+            let max_amount = rest
+                .get(offset..offset+8)
+                .and_then(|slice| slice.try_into().ok())
+                .map(u64::from_le_bytes)
+                .ok_or(AppError::InvalidInstruction)?;
+
+            max_amounts.push(max_amount);
+            offset += 8;
+        }
+
+        Self::AddLiquidityBalanced { lpt_out, max_amounts }
+      }
+      15 => {
+        // The amounts fill the data up to the LPT bound in the last 8 bytes
+        let amounts_size = rest
+          .len()
+          .checked_sub(8)
+          .filter(|size| size % 8 == 0)
+          .ok_or(AppError::InvalidInstruction)?;
+        let mut offset = 0;
+        let mut amounts = Vec::new();
+
+        while offset < amounts_size {
+            let amount = rest
+                .get(offset..offset+8)
+                .and_then(|slice| slice.try_into().ok())
+                .map(u64::from_le_bytes)
+                .ok_or(AppError::InvalidInstruction)?;
+
+            amounts.push(amount);
+            offset += 8;
+        }
+        let max_lpt = rest
+          .get(amounts_size..)
+          .and_then(|slice| slice.try_into().ok())
+          .map(u64::from_le_bytes)
+          .ok_or(AppError::InvalidInstruction)?;
+
+        Self::RemoveLiquidityImbalanced { amounts, max_lpt }
+      }
+      16 => {
+        let earning = rest
+          .get(..8)
+          .and_then(|slice| slice.try_into().ok())
+          .map(u64::from_le_bytes)
+          .ok_or(AppError::InvalidInstruction)?;
+        Self::InitializeAllowlist { earning }
+      }
+      17 => Self::AddAllowlistTrader,
+      18 => Self::RemoveAllowlistTrader,
+      19 => {
+        let earning = rest
+          .get(..8)
+          .and_then(|slice| slice.try_into().ok())
+          .map(u64::from_le_bytes)
+          .ok_or(AppError::InvalidInstruction)?;
+        Self::SetAllowlistEarning { earning }
+      }
+      20 => {
+        let share = rest
+          .get(..8)
+          .and_then(|slice| slice.try_into().ok())
+          .map(u64::from_le_bytes)
+          .ok_or(AppError::InvalidInstruction)?;
+        Self::SetReferralShare { share }
+      }
+      21 => {
+        let rest_size = rest.len();
+        let mut offset = 0;
+        let mut fee_tiers = Vec::new();
+
+        while (offset + 16) <= rest_size {
+            let volume = rest
+                .get(offset..offset+8)
+                .and_then(|slice| slice.try_into().ok())
+                .map(u64::from_le_bytes)
+                .ok_or(AppError::InvalidInstruction)?;
+            let discount = rest
+                .get(offset+8..offset+16)
+                .and_then(|slice| slice.try_into().ok())
+                .map(u64::from_le_bytes)
+                .ok_or(AppError::InvalidInstruction)?;
+
+            fee_tiers.push(FeeTier { volume, discount });
+            offset += 16;
+        }
+
+        Self::SetFeeTiers { fee_tiers }
+      }
+      22 => Self::InitializeTraderVolume,
+      23 => {
+        let rest_size = rest.len();
+        let mut offset = 0;
+        let mut holder_tiers = Vec::new();
+
+        while (offset + 16) <= rest_size {
+            let balance = rest
+                .get(offset..offset+8)
+                .and_then(|slice| slice.try_into().ok())
+                .map(u64::from_le_bytes)
+                .ok_or(AppError::InvalidInstruction)?;
+            let discount = rest
+                .get(offset+8..offset+16)
+                .and_then(|slice| slice.try_into().ok())
+                .map(u64::from_le_bytes)
+                .ok_or(AppError::InvalidInstruction)?;
+
+            holder_tiers.push(HolderTier { balance, discount });
+            offset += 16;
+        }
+
+        Self::SetHolderTiers { holder_tiers }
+      }
+      24 => {
+        let is_enabled = match rest.first() {
+          Some(0) => false,
+          Some(1) => true,
+          _ => return Err(AppError::InvalidInstruction.into()),
+        };
+        let min_fee = rest
+          .get(1..9)
+          .and_then(|slice| slice.try_into().ok())
+          .map(u64::from_le_bytes)
+          .ok_or(AppError::InvalidInstruction)?;
+        let max_fee = rest
+          .get(9..17)
+          .and_then(|slice| slice.try_into().ok())
+          .map(u64::from_le_bytes)
+          .ok_or(AppError::InvalidInstruction)?;
+        Self::SetDynamicFee { is_enabled, min_fee, max_fee }
+      }
+      25 => {
+        let max_deviation = rest
+          .get(..2)
+          .and_then(|slice| slice.try_into().ok())
+          .map(u16::from_le_bytes)
+          .ok_or(AppError::InvalidInstruction)?;
+        Self::SetCircuitBreaker { max_deviation }
+      }
+      26 => {
+        let max_bid_ratio = rest
+          .get(..2)
+          .and_then(|slice| slice.try_into().ok())
+          .map(u16::from_le_bytes)
+          .ok_or(AppError::InvalidInstruction)?;
+        let max_ask_ratio = rest
+          .get(2..4)
+          .and_then(|slice| slice.try_into().ok())
+          .map(u16::from_le_bytes)
+          .ok_or(AppError::InvalidInstruction)?;
+        Self::SetTradeLimits { max_bid_ratio, max_ask_ratio }
+      }
+      27 => {
+        let is_enabled = match rest.first() {
+          Some(0) => false,
+          Some(1) => true,
+          _ => return Err(AppError::InvalidInstruction.into()),
+        };
+        Self::SetAntiSandwich { is_enabled }
+      }
+      28 => Self::InitializeRouterRegistry,
+      29 => Self::AddRouter,
+      30 => Self::RemoveRouter,
+      31 => {
+        let max_staleness = rest
+          .get(..8)
+          .and_then(|slice| slice.try_into().ok())
+          .map(u64::from_le_bytes)
+          .ok_or(AppError::InvalidInstruction)?;
+        let spread = rest
+          .get(8..10)
+          .and_then(|slice| slice.try_into().ok())
+          .map(u16::from_le_bytes)
+          .ok_or(AppError::InvalidInstruction)?;
+        let max_confidence = rest
+          .get(10..12)
+          .and_then(|slice| slice.try_into().ok())
+          .map(u16::from_le_bytes)
+          .ok_or(AppError::InvalidInstruction)?;
+        Self::SetPricePeg { max_staleness, spread, max_confidence }
+      }
+      32 => {
+        let rate = rest
+          .get(..8)
+          .and_then(|slice| slice.try_into().ok())
+          .map(u64::from_le_bytes)
+          .ok_or(AppError::InvalidInstruction)?;
+        Self::InitializeRateProvider { rate }
+      }
+      33 => {
+        let rate = rest
+          .get(..8)
+          .and_then(|slice| slice.try_into().ok())
+          .map(u64::from_le_bytes)
+          .ok_or(AppError::InvalidInstruction)?;
+        Self::UpdateRate { rate }
+      }
+      34 => {
+        let rate_duration = rest
+          .get(..8)
+          .and_then(|slice| slice.try_into().ok())
+          .map(i64::from_le_bytes)
+          .ok_or(AppError::InvalidInstruction)?;
+        Self::SetPoolRateProvider { rate_duration }
+      }
+      35 => Self::RefreshRates,
+      36 => {
+        let curve = rest
+          .first()
+          .and_then(|&curve| Curve::try_from_primitive(curve).ok())
+          .ok_or(AppError::InvalidInstruction)?;
+        Self::SetCurve { curve }
+      }
+      37 => {
+        let is_enabled = match rest.first() {
+          Some(0) => false,
+          Some(1) => true,
+          _ => return Err(AppError::InvalidInstruction.into()),
+        };
+        Self::SetEarning { is_enabled }
+      }
+      38 => {
+        let minimum = rest
+          .get(..8)
+          .and_then(|slice| slice.try_into().ok())
+          .map(u64::from_le_bytes)
+          .ok_or(AppError::InvalidInstruction)?;
+        Self::SetMinimumReserve { minimum }
+      }
+      39 => Self::RetirePoolToken,
+      40 => {
+        let amount = rest
+          .get(..8)
+          .and_then(|slice| slice.try_into().ok())
+          .map(u64::from_le_bytes)
+          .ok_or(AppError::InvalidInstruction)?;
+        let max_lpt = rest
+          .get(8..16)
+          .and_then(|slice| slice.try_into().ok())
+          .map(u64::from_le_bytes)
+          .ok_or(AppError::InvalidInstruction)?;
+        Self::ExitPoolToken { amount, max_lpt }
+      }
       _ => return Err(AppError::InvalidInstruction.into()),
     })
   }
@@ -8,6 +8,7 @@ use solana_program::{
   pub struct ISPLATA {}
   
   impl ISPLATA {
+    #[allow(clippy::too_many_arguments)]
     pub fn initialize_account(
       funding_acc: Pubkey,
       target_acc: Pubkey,
@@ -21,14 +22,44 @@ use solana_program::{
       // Build data
       let data = Vec::with_capacity(size_of::<Self>());
       // Build accounts
-      let mut accounts = Vec::with_capacity(7);
-      accounts.push(AccountMeta::new(funding_acc, true));
-      accounts.push(AccountMeta::new(target_acc, false));
-      accounts.push(AccountMeta::new_readonly(owner, false));
-      accounts.push(AccountMeta::new_readonly(mint_acc, false));
-      accounts.push(AccountMeta::new_readonly(system_program, false));
-      accounts.push(AccountMeta::new_readonly(splt_program, false));
-      accounts.push(AccountMeta::new_readonly(sysvar_rent_acc, false));
+      let accounts = vec![
+        AccountMeta::new(funding_acc, true),
+        AccountMeta::new(target_acc, false),
+        AccountMeta::new_readonly(owner, false),
+        AccountMeta::new_readonly(mint_acc, false),
+        AccountMeta::new_readonly(system_program, false),
+        AccountMeta::new_readonly(splt_program, false),
+        AccountMeta::new_readonly(sysvar_rent_acc, false),
+      ];
+      // Return
+      Ok(Instruction {
+        program_id,
+        accounts,
+        data,
+      })
+    }
+
+    pub fn initialize_account_idempotent(
+      funding_acc: Pubkey,
+      target_acc: Pubkey,
+      owner: Pubkey,
+      mint_acc: Pubkey,
+      system_program: Pubkey,
+      splt_program: Pubkey,
+      program_id: Pubkey,
+    ) -> Result<Instruction, ProgramError> {
+      // Build data
+      let mut data = Vec::with_capacity(size_of::<Self>());
+      data.push(1);
+      // Build accounts
+      let accounts = vec![
+        AccountMeta::new(funding_acc, true),
+        AccountMeta::new(target_acc, false),
+        AccountMeta::new_readonly(owner, false),
+        AccountMeta::new_readonly(mint_acc, false),
+        AccountMeta::new_readonly(system_program, false),
+        AccountMeta::new_readonly(splt_program, false),
+      ];
       // Return
       Ok(Instruction {
         program_id,
@@ -37,4 +68,3 @@ use solana_program::{
       })
     }
   }
-  
\ No newline at end of file
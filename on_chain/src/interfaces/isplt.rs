@@ -22,9 +22,10 @@ use solana_program::{
       data.extend_from_slice(&owner.to_bytes());
       data.push(1);
       data.extend_from_slice(&freeze_authority.to_bytes());
-      let mut accounts = Vec::with_capacity(2);
-      accounts.push(AccountMeta::new(mint_acc, false));
-      accounts.push(AccountMeta::new_readonly(sysvar_rent_acc, false));
+      let accounts = vec![
+        AccountMeta::new(mint_acc, false),
+        AccountMeta::new_readonly(sysvar_rent_acc, false),
+      ];
       Ok(Instruction {
         program_id,
         accounts,
@@ -40,11 +41,32 @@ use solana_program::{
     ) -> Result<Instruction, ProgramError> {
       let mut data = Vec::with_capacity(size_of::<Self>());
       data.push(1);
-      let mut accounts = Vec::with_capacity(4);
-      accounts.push(AccountMeta::new(target_acc, false));
-      accounts.push(AccountMeta::new_readonly(mint_acc, false));
-      accounts.push(AccountMeta::new_readonly(owner, false));
-      accounts.push(AccountMeta::new_readonly(sysvar_rent_acc, false));
+      let accounts = vec![
+        AccountMeta::new(target_acc, false),
+        AccountMeta::new_readonly(mint_acc, false),
+        AccountMeta::new_readonly(owner, false),
+        AccountMeta::new_readonly(sysvar_rent_acc, false),
+      ];
+      Ok(Instruction {
+        program_id,
+        accounts,
+        data,
+      })
+    }
+    // Same as initialize_account, without the rent sysvar
+    pub fn initialize_account3(
+      target_acc: Pubkey,
+      mint_acc: Pubkey,
+      owner: Pubkey,
+      program_id: Pubkey,
+    ) -> Result<Instruction, ProgramError> {
+      let mut data = Vec::with_capacity(size_of::<Self>());
+      data.push(18);
+      data.extend_from_slice(&owner.to_bytes());
+      let accounts = vec![
+        AccountMeta::new(target_acc, false),
+        AccountMeta::new_readonly(mint_acc, false),
+      ];
       Ok(Instruction {
         program_id,
         accounts,
@@ -61,10 +83,11 @@ use solana_program::{
       let mut data = Vec::with_capacity(size_of::<Self>());
       data.push(3);
       data.extend_from_slice(&amount.to_le_bytes());
-      let mut accounts = Vec::with_capacity(3);
-      accounts.push(AccountMeta::new(src_acc, false));
-      accounts.push(AccountMeta::new(dst_acc, false));
-      accounts.push(AccountMeta::new_readonly(owner, true));
+      let accounts = vec![
+        AccountMeta::new(src_acc, false),
+        AccountMeta::new(dst_acc, false),
+        AccountMeta::new_readonly(owner, true),
+      ];
       Ok(Instruction {
         program_id,
         accounts,
@@ -72,6 +95,32 @@ use solana_program::{
       })
     }
     
+    pub fn transfer_checked(
+      amount: u64,
+      decimals: u8,
+      src_acc: Pubkey,
+      mint_acc: Pubkey,
+      dst_acc: Pubkey,
+      owner: Pubkey,
+      program_id: Pubkey,
+    ) -> Result<Instruction, ProgramError> {
+      let mut data = Vec::with_capacity(size_of::<Self>());
+      data.push(12);
+      data.extend_from_slice(&amount.to_le_bytes());
+      data.push(decimals);
+      let accounts = vec![
+        AccountMeta::new(src_acc, false),
+        AccountMeta::new_readonly(mint_acc, false),
+        AccountMeta::new(dst_acc, false),
+        AccountMeta::new_readonly(owner, true),
+      ];
+      Ok(Instruction {
+        program_id,
+        accounts,
+        data,
+      })
+    }
+
     pub fn mint_to(
       amount: u64,
       mint_acc: Pubkey,
@@ -82,10 +131,11 @@ use solana_program::{
       let mut data = Vec::with_capacity(size_of::<Self>());
       data.push(7);
       data.extend_from_slice(&amount.to_le_bytes());
-      let mut accounts = Vec::with_capacity(3);
-      accounts.push(AccountMeta::new(mint_acc, false));
-      accounts.push(AccountMeta::new(dst_acc, false));
-      accounts.push(AccountMeta::new_readonly(owner, true));
+      let accounts = vec![
+        AccountMeta::new(mint_acc, false),
+        AccountMeta::new(dst_acc, false),
+        AccountMeta::new_readonly(owner, true),
+      ];
       Ok(Instruction {
         program_id,
         accounts,
@@ -103,10 +153,11 @@ use solana_program::{
       let mut data = Vec::with_capacity(size_of::<Self>());
       data.push(8);
       data.extend_from_slice(&amount.to_le_bytes());
-      let mut accounts = Vec::with_capacity(3);
-      accounts.push(AccountMeta::new(src_acc, false));
-      accounts.push(AccountMeta::new(mint_acc, false));
-      accounts.push(AccountMeta::new_readonly(owner, true));
+      let accounts = vec![
+        AccountMeta::new(src_acc, false),
+        AccountMeta::new(mint_acc, false),
+        AccountMeta::new_readonly(owner, true),
+      ];
       Ok(Instruction {
         program_id,
         accounts,
@@ -114,6 +165,22 @@ use solana_program::{
       })
     }
     
+    pub fn sync_native(
+      native_acc: Pubkey,
+      program_id: Pubkey,
+    ) -> Result<Instruction, ProgramError> {
+      let mut data = Vec::with_capacity(size_of::<Self>());
+      data.push(17);
+      let accounts = vec![
+        AccountMeta::new(native_acc, false),
+      ];
+      Ok(Instruction {
+        program_id,
+        accounts,
+        data,
+      })
+    }
+
     pub fn close_account(
       src_acc: Pubkey,
       dst_acc: Pubkey,
@@ -122,10 +189,31 @@ use solana_program::{
     ) -> Result<Instruction, ProgramError> {
       let mut data = Vec::with_capacity(size_of::<Self>());
       data.push(9);
-      let mut accounts = Vec::with_capacity(3);
-      accounts.push(AccountMeta::new(src_acc, false));
-      accounts.push(AccountMeta::new(dst_acc, false));
-      accounts.push(AccountMeta::new_readonly(owner, true));
+      let accounts = vec![
+        AccountMeta::new(src_acc, false),
+        AccountMeta::new(dst_acc, false),
+        AccountMeta::new_readonly(owner, true),
+      ];
+      Ok(Instruction {
+        program_id,
+        accounts,
+        data,
+      })
+    }
+
+    // Token-2022 transfer fee extension, moves the fees withheld in src_acc to the mint
+    pub fn harvest_withheld_tokens_to_mint(
+      mint_acc: Pubkey,
+      src_acc: Pubkey,
+      program_id: Pubkey,
+    ) -> Result<Instruction, ProgramError> {
+      let mut data = Vec::with_capacity(size_of::<Self>());
+      data.push(26);
+      data.push(4);
+      let accounts = vec![
+        AccountMeta::new(mint_acc, false),
+        AccountMeta::new(src_acc, false),
+      ];
       Ok(Instruction {
         program_id,
         accounts,
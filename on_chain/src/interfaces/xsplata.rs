@@ -6,6 +6,7 @@ use solana_program::{
 pub struct XSPLATA {}
 
 impl XSPLATA {
+  #[allow(clippy::too_many_arguments)]
   pub fn initialize_account<'a>(
     funding_acc: &AccountInfo<'a>,
     target_acc: &AccountInfo<'a>,
@@ -43,4 +44,40 @@ impl XSPLATA {
     )?;
     Ok(())
   }
+
+  #[allow(clippy::too_many_arguments)]
+  pub fn initialize_account_idempotent<'a>(
+    funding_acc: &AccountInfo<'a>,
+    target_acc: &AccountInfo<'a>,
+    owner: &AccountInfo<'a>,
+    mint_acc: &AccountInfo<'a>,
+    system_program: &AccountInfo<'a>,
+    splt_program: &AccountInfo<'a>,
+    splata_program: &AccountInfo<'a>,
+    seed: &[&[&[u8]]],
+  ) -> ProgramResult {
+    let ix = ISPLATA::initialize_account_idempotent(
+      *funding_acc.key,
+      *target_acc.key,
+      *owner.key,
+      *mint_acc.key,
+      *system_program.key,
+      *splt_program.key,
+      *splata_program.key,
+    )?;
+    invoke_signed(
+      &ix,
+      &[
+        funding_acc.clone(),
+        target_acc.clone(),
+        owner.clone(),
+        mint_acc.clone(),
+        system_program.clone(),
+        splt_program.clone(),
+        splata_program.clone(),
+      ],
+      seed,
+    )?;
+    Ok(())
+  }
 }
@@ -1,6 +1,6 @@
 use crate::interfaces::isplt::ISPLT;
 use solana_program::{
-  account_info::AccountInfo, entrypoint::ProgramResult, program::invoke_signed,
+  account_info::AccountInfo, entrypoint::ProgramResult, program::{invoke, invoke_signed},
 };
 
 pub struct XSPLT {}
@@ -64,6 +64,20 @@ impl XSPLT {
     Ok(())
   }
 
+  pub fn initialize_account3<'a>(
+    target_acc: &AccountInfo<'a>,
+    mint_acc: &AccountInfo<'a>,
+    owner: &AccountInfo<'a>,
+    splt_program: &AccountInfo<'a>,
+  ) -> ProgramResult {
+    let ix = ISPLT::initialize_account3(*target_acc.key, *mint_acc.key, *owner.key, *splt_program.key)?;
+    invoke(
+      &ix,
+      &[target_acc.clone(), mint_acc.clone(), splt_program.clone()],
+    )?;
+    Ok(())
+  }
+
   pub fn transfer<'a>(
     amount: u64,
     src_acc: &AccountInfo<'a>,
@@ -92,6 +106,40 @@ impl XSPLT {
     Ok(())
   }
 
+  #[allow(clippy::too_many_arguments)]
+  pub fn transfer_checked<'a>(
+    amount: u64,
+    decimals: u8,
+    src_acc: &AccountInfo<'a>,
+    mint_acc: &AccountInfo<'a>,
+    dst_acc: &AccountInfo<'a>,
+    owner: &AccountInfo<'a>,
+    splt_program: &AccountInfo<'a>,
+    seed: &[&[&[u8]]],
+  ) -> ProgramResult {
+    let ix = ISPLT::transfer_checked(
+      amount,
+      decimals,
+      *src_acc.key,
+      *mint_acc.key,
+      *dst_acc.key,
+      *owner.key,
+      *splt_program.key,
+    )?;
+    invoke_signed(
+      &ix,
+      &[
+        src_acc.clone(),
+        mint_acc.clone(),
+        dst_acc.clone(),
+        owner.clone(),
+        splt_program.clone(),
+      ],
+      seed,
+    )?;
+    Ok(())
+  }
+
   pub fn mint_to<'a>(
     amount: u64,
     mint_acc: &AccountInfo<'a>,
@@ -148,11 +196,21 @@ impl XSPLT {
     Ok(())
   }
   
+  pub fn sync_native<'a>(
+    native_acc: &AccountInfo<'a>,
+    splt_program: &AccountInfo<'a>,
+    seed: &[&[&[u8]]],
+  ) -> ProgramResult {
+    let ix = ISPLT::sync_native(*native_acc.key, *splt_program.key)?;
+    invoke_signed(&ix, &[native_acc.clone(), splt_program.clone()], seed)?;
+    Ok(())
+  }
+
   pub fn close_account<'a>(
     src_acc: &AccountInfo<'a>,
     dst_acc: &AccountInfo<'a>,
     owner: &AccountInfo<'a>,
-    splt_program: &Accou    ntInfo<'a>,
+    splt_program: &AccountInfo<'a>,
     seed: &[&[&[u8]]],
   ) -> ProgramResult {
     let ix = ISPLT::close_account(*src_acc.key, *dst_acc.key, *owner.key, *splt_program.key)?;
@@ -168,4 +226,17 @@ impl XSPLT {
     )?;
     Ok(())
   }
+
+  pub fn harvest_withheld_tokens_to_mint<'a>(
+    mint_acc: &AccountInfo<'a>,
+    src_acc: &AccountInfo<'a>,
+    splt_program: &AccountInfo<'a>,
+  ) -> ProgramResult {
+    let ix = ISPLT::harvest_withheld_tokens_to_mint(*mint_acc.key, *src_acc.key, *splt_program.key)?;
+    invoke(
+      &ix,
+      &[mint_acc.clone(), src_acc.clone(), splt_program.clone()],
+    )?;
+    Ok(())
+  }
 }
@@ -1,3 +1,6 @@
+// The entrypoint macros test cfg values that only the Solana toolchain declares
+#![allow(unexpected_cfgs)]
+
 pub mod entrypoint;
 pub mod error;
 pub mod helper;
@@ -1,18 +1,45 @@
 use crate::error::AppError;
-use crate::helper::{oracle::Oracle};
-use crate::instruction::AppInstruction;
+use crate::helper::oracle::{Oracle, EARNING, FEE};
+use crate::instruction::{
+    AppInstruction, SWAP_POOL_INDEX, SWAP_TAG, SWAP_TREASURY_ASK_INDEX, SWAP_TREASURY_BID_INDEX,
+};
 use crate::interfaces::{xsplata::XSPLATA, xsplt::XSPLT};
 use crate::schema::{
-    mint::Mint,
-    pool::{Pool, PoolState, MAX_TOKEN_COUNT}, 
+    account::Account,
+    allowlist::Allowlist,
+    minimum::{MinimumReserve, MINIMUM_RESERVE_SEED},
+    mint::{native_mint, spl_associated_token_account, spl_token, spl_token_2022, Mint, ACCOUNT_LEN},
+    multisig::{Multisig, MAX_SIGNERS, MIN_SIGNERS},
+    pool::{
+        Curve, FeeTier, HolderTier, Pool, PoolState, FEE_TIER_COUNT, HOLDER_TIER_COUNT, LOCKER_SEED,
+        MAX_DEVIATION_BPS, MAX_DYNAMIC_FEE, MAX_FEE_DISCOUNT, MAX_REFERRAL_SHARE, MAX_TOKEN_COUNT,
+        MAX_TRADE_RATIO_BPS, MINIMUM_LIQUIDITY, PAUSE_ALL, PAUSE_DEPOSIT, PAUSE_FROZEN, PAUSE_SWAP,
+        PAUSE_WITHDRAW,
+    },
+    price::Price,
+    rate::RateProvider,
+    registry::RouterRegistry,
+    volume::{TraderVolume, SECONDS_PER_DAY},
 };
+use num_enum::TryFromPrimitive;
 use solana_program::{
     account_info::{next_account_info, AccountInfo},
+    clock::Clock,
     entrypoint::ProgramResult,
     msg,
+    program::{invoke, invoke_signed},
+    program_option::COption,
+    program_error::ProgramError,
     program_pack::{IsInitialized, Pack},
     pubkey::{Pubkey, PubkeyError},
+    rent::Rent,
+    system_instruction, system_program,
+    sysvar::{
+        instructions::{load_current_index_checked, load_instruction_at_checked},
+        Sysvar,
+    },
 };
+use std::slice::Iter;
 
 pub struct Processor {}
   
@@ -33,14 +60,15 @@ impl Processor {
 
       AppInstruction::AddLiquidity {
         deltas,
+        min_lpt,
       } => {
         msg!("Calling AddLiquidity function");
-        Self::add_liquidity(deltas, program_id, accounts)
+        Self::add_liquidity(deltas, min_lpt, program_id, accounts)
       }
 
-      AppInstruction::RemoveLiquidity { lpt } => {
+      AppInstruction::RemoveLiquidity { lpt, min_amounts } => {
         msg!("Calling RemoveLiquidity function");
-        Self::remove_liquidity(lpt, program_id, accounts)
+        Self::remove_liquidity(lpt, min_amounts, program_id, accounts)
       }
 
       AppInstruction::Swap { amount, limit } => {
@@ -48,12 +76,12 @@ impl Processor {
         Self::swap(amount, limit, program_id, accounts)
       }
 
-      AppInstruction::FreezePool {} => {
+      AppInstruction::FreezePool => {
         msg!("Calling FreezePool function");
         Self::freeze_pool(program_id, accounts)
       }
 
-      AppInstruction::ThawPool {} => {
+      AppInstruction::ThawPool => {
         msg!("Calling ThawPool function");
         Self::thaw_pool(program_id, accounts)
       }
@@ -63,16 +91,181 @@ impl Processor {
         Self::earn(amount, program_id, accounts)
       }
 
-      AppInstruction::TransferPoolOwnership {} => {
+      AppInstruction::TransferPoolOwnership => {
         msg!("Calling TransferPoolOwnership function");
         Self::transfer_pool_ownership(program_id, accounts)
       }
+
+      AppInstruction::InitializeMultisig { m } => {
+        msg!("Calling InitializeMultisig function");
+        Self::initialize_multisig(m, program_id, accounts)
+      }
+
+      AppInstruction::TogglePause { flags } => {
+        msg!("Calling TogglePause function");
+        Self::toggle_pause(flags, program_id, accounts)
+      }
+
+      AppInstruction::UpdatePoolState { state } => {
+        msg!("Calling UpdatePoolState function");
+        Self::update_pool_state(state, program_id, accounts)
+      }
+
+      AppInstruction::AddPoolToken {
+        amount,
+        value,
+        min_lpt,
+      } => {
+        msg!("Calling AddPoolToken function");
+        Self::add_pool_token(amount, value, min_lpt, program_id, accounts)
+      }
+
+      AppInstruction::RemovePoolToken => {
+        msg!("Calling RemovePoolToken function");
+        Self::remove_pool_token(program_id, accounts)
+      }
+
+      AppInstruction::ClosePool => {
+        msg!("Calling ClosePool function");
+        Self::close_pool(program_id, accounts)
+      }
+
+      AppInstruction::AddLiquidityBalanced { lpt_out, max_amounts } => {
+        msg!("Calling AddLiquidityBalanced function");
+        Self::add_liquidity_balanced(lpt_out, max_amounts, program_id, accounts)
+      }
+
+      AppInstruction::RemoveLiquidityImbalanced { amounts, max_lpt } => {
+        msg!("Calling RemoveLiquidityImbalanced function");
+        Self::remove_liquidity_imbalanced(amounts, max_lpt, program_id, accounts)
+      }
+
+      AppInstruction::InitializeAllowlist { earning } => {
+        msg!("Calling InitializeAllowlist function");
+        Self::initialize_allowlist(earning, program_id, accounts)
+      }
+
+      AppInstruction::AddAllowlistTrader => {
+        msg!("Calling AddAllowlistTrader function");
+        Self::add_allowlist_trader(program_id, accounts)
+      }
+
+      AppInstruction::RemoveAllowlistTrader => {
+        msg!("Calling RemoveAllowlistTrader function");
+        Self::remove_allowlist_trader(program_id, accounts)
+      }
+
+      AppInstruction::SetAllowlistEarning { earning } => {
+        msg!("Calling SetAllowlistEarning function");
+        Self::set_allowlist_earning(earning, program_id, accounts)
+      }
+
+      AppInstruction::SetReferralShare { share } => {
+        msg!("Calling SetReferralShare function");
+        Self::set_referral_share(share, program_id, accounts)
+      }
+
+      AppInstruction::SetFeeTiers { fee_tiers } => {
+        msg!("Calling SetFeeTiers function");
+        Self::set_fee_tiers(fee_tiers, program_id, accounts)
+      }
+
+      AppInstruction::InitializeTraderVolume => {
+        msg!("Calling InitializeTraderVolume function");
+        Self::initialize_trader_volume(program_id, accounts)
+      }
+
+      AppInstruction::SetHolderTiers { holder_tiers } => {
+        msg!("Calling SetHolderTiers function");
+        Self::set_holder_tiers(holder_tiers, program_id, accounts)
+      }
+
+      AppInstruction::SetDynamicFee { is_enabled, min_fee, max_fee } => {
+        msg!("Calling SetDynamicFee function");
+        Self::set_dynamic_fee(is_enabled, min_fee, max_fee, program_id, accounts)
+      }
+
+      AppInstruction::SetCircuitBreaker { max_deviation } => {
+        msg!("Calling SetCircuitBreaker function");
+        Self::set_circuit_breaker(max_deviation, program_id, accounts)
+      }
+
+      AppInstruction::SetTradeLimits { max_bid_ratio, max_ask_ratio } => {
+        msg!("Calling SetTradeLimits function");
+        Self::set_trade_limits(max_bid_ratio, max_ask_ratio, program_id, accounts)
+      }
+
+      AppInstruction::SetAntiSandwich { is_enabled } => {
+        msg!("Calling SetAntiSandwich function");
+        Self::set_anti_sandwich(is_enabled, program_id, accounts)
+      }
+
+      AppInstruction::InitializeRouterRegistry => {
+        msg!("Calling InitializeRouterRegistry function");
+        Self::initialize_router_registry(program_id, accounts)
+      }
+
+      AppInstruction::AddRouter => {
+        msg!("Calling AddRouter function");
+        Self::add_router(program_id, accounts)
+      }
+
+      AppInstruction::RemoveRouter => {
+        msg!("Calling RemoveRouter function");
+        Self::remove_router(program_id, accounts)
+      }
+
+      AppInstruction::SetPricePeg { max_staleness, spread, max_confidence } => {
+        msg!("Calling SetPricePeg function");
+        Self::set_price_peg(max_staleness, spread, max_confidence, program_id, accounts)
+      }
+
+      AppInstruction::InitializeRateProvider { rate } => {
+        msg!("Calling InitializeRateProvider function");
+        Self::initialize_rate_provider(rate, program_id, accounts)
+      }
+
+      AppInstruction::UpdateRate { rate } => {
+        msg!("Calling UpdateRate function");
+        Self::update_rate(rate, program_id, accounts)
+      }
+
+      AppInstruction::SetPoolRateProvider { rate_duration } => {
+        msg!("Calling SetPoolRateProvider function");
+        Self::set_pool_rate_provider(rate_duration, program_id, accounts)
+      }
+
+      AppInstruction::RefreshRates => {
+        msg!("Calling RefreshRates function");
+        Self::refresh_rates(program_id, accounts)
+      }
+
+      AppInstruction::SetCurve { curve } => {
+        msg!("Calling SetCurve function");
+        Self::set_curve(curve, program_id, accounts)
+      }
+
+      AppInstruction::SetEarning { is_enabled } => {
+        msg!("Calling SetEarning function");
+        Self::set_earning(is_enabled, program_id, accounts)
+      }
+      AppInstruction::SetMinimumReserve { minimum } => {
+        msg!("Calling SetMinimumReserve function");
+        Self::set_minimum_reserve(minimum, program_id, accounts)
+      }
+      AppInstruction::RetirePoolToken => {
+        msg!("Calling RetirePoolToken function");
+        Self::retire_pool_token(program_id, accounts)
+      }
+      AppInstruction::ExitPoolToken { amount, max_lpt } => {
+        msg!("Calling ExitPoolToken function");
+        Self::exit_pool_token(amount, max_lpt, program_id, accounts)
+      }
     }
   }
 
   pub fn initialize_pool(
     reserves: Vec<u64>,
-    weights: Vec<64>,
     program_id: &Pubkey,
     accounts: &[AccountInfo],
   ) -> ProgramResult {
@@ -82,43 +275,40 @@ impl Processor {
     let pool_acc = next_account_info(accounts_iter)?;
     let lpt_acc = next_account_info(accounts_iter)?;
     let mint_lpt_acc = next_account_info(accounts_iter)?;
-    let vault_acc = next_account_info(accounts_iter)?; //owned by treasurer
     let proof_acc = next_account_info(accounts_iter)?; // program_id xor treasurer xor pool_id
+    let lock_acc = next_account_info(accounts_iter)?; // holds the locked LPT
+    let locker = next_account_info(accounts_iter)?; // owner of lock_acc, nobody signs for it
 
-    let treasurer = next_account_info(accounts_iter)?; //owner of treasury_s,a,b_acc
+    let treasurer = next_account_info(accounts_iter)?; //owner of treasury_accs
     let system_program = next_account_info(accounts_iter)?;
     let splt_program = next_account_info(accounts_iter)?;
+    let splt_2022_program = next_account_info(accounts_iter)?; // owner of Token-2022 mints
 
     let sysvar_rent_acc = next_account_info(accounts_iter)?;
-    let splata_program = next_account_info(accounts_iter)?; //create treasury_s,a,b_acc
-
-    let mut src_accs = Vec::new();
-    let mut mint_accs = Vec::new();
-    let mut treasury_accs = Vec::new();
-
-    let mut token_count = 0;
-    for accounts in accounts_iter.chunks(3) {
-      if accounts.len() < 3 {
-        break;
-      } else {
-        let src_acc = next_account_info(accounts)?;
-        let mint_acc = next_account_info(accounts)?;
-        let treasury_acc = next_account_info(accounts)?;
-    
-        src_accs.push(src_acc);
-        mint_accs.push(mint_acc);
-        treasury_accs.push(treasury_acc);
+    let splata_program = next_account_info(accounts_iter)?; //create treasury_accs
 
-        token_count++;
-      }      
+    // src_acc, mint_acc, treasury_acc, vault_acc, minimum_acc for every token of the pool
+    let token_count = reserves.len();
+    if token_count == 0 || token_count > MAX_TOKEN_COUNT {
+      return Err(AppError::InvalidInstruction.into());
     }
-
-    if token_count > MAX_TOKEN_COUNT || reserves.len() != src_acc.len() || src_acc.len() != mint_acc.len() || mint_acc.len() != treasury_acc.len() {
-      return Err(AppError::ConstructorOnce.into());
+    let mut src_accs = Vec::with_capacity(token_count);
+    let mut mint_accs = Vec::with_capacity(token_count);
+    let mut treasury_accs = Vec::with_capacity(token_count);
+    let mut vault_accs = Vec::with_capacity(token_count);
+    let mut minimum_accs = Vec::with_capacity(token_count);
+    for _ in 0..token_count {
+      src_accs.push(next_account_info(accounts_iter)?);
+      mint_accs.push(next_account_info(accounts_iter)?);
+      treasury_accs.push(next_account_info(accounts_iter)?);
+      vault_accs.push(next_account_info(accounts_iter)?); //owned by treasurer
+      minimum_accs.push(next_account_info(accounts_iter)?); // derived from the mint, may not exist
     }
 
+    Self::is_token_programs(splt_program, splt_2022_program)?;
     Self::is_program(program_id, &[pool_acc])?;
-    Self::is_signer(&[payer, pool_acc, vault_acc])?;
+    Self::is_signer(&[payer, pool_acc])?;
+    Self::is_signer(&vault_accs)?;
 
     let mut pool_data = Pool::unpack_unchecked(&pool_acc.data.borrow())?;
     let mint_lpt_data = Mint::unpack_unchecked(&mint_lpt_acc.data.borrow())?;
@@ -129,7 +319,7 @@ impl Processor {
 
     let pool_treasurer_xor_key = match Self::key_xor(pool_acc.key, treasurer.key) {
       Ok(pool_treasurer_xor_key) => pool_treasurer_xor_key,
-      Err(e) => {
+      Err(_) => {
         msg!("processor-initialize_pool: xor pool_acc-treasurer error");
         return Err(AppError::ConstructorOnce.into());
       }
@@ -137,7 +327,7 @@ impl Processor {
 
     let program_xor_key = match Self::key_xor(program_id, &pool_treasurer_xor_key) {
       Ok(program_xor_key) => program_xor_key,
-      Err(e) => {
+      Err(_) => {
         msg!("processor-initialize_pool: xor program_id-pool_treasurer error");
         return Err(AppError::ConstructorOnce.into());
       }
@@ -147,44 +337,50 @@ impl Processor {
       return Err(AppError::InvalidMint.into());
     }
 
-    for itemReserve in &reserves {
-      if itemReserve == 0 {
-        return Err(AppError::ZeroValue.into());
-      }
+    let (locker_key, _) = Pubkey::find_program_address(&[&pool_acc.key.to_bytes(), LOCKER_SEED], program_id);
+    if *locker.key != locker_key {
+      return Err(AppError::InvalidOwner.into());
     }
 
+    let splt_programs = &[splt_program, splt_2022_program];
+    let mut reserves = reserves;
     for i in 0..token_count {
-      let src_acc = src_accs.get(i).ok_or(AppError::MissingAccount)?;
-      let mint_acc = mint_accs.get(i).ok_or(AppError::MissingAccount)?;
-      let treasury_acc = treasury_accs.get(i).ok_or(AppError::MissingAccount)?;      
-      let reserve = reserves.get(i).ok_or(AppError::MissingAccount)?;
       // Initialize treasury_acc
       XSPLATA::initialize_account(
         payer,
-        treasury_acc,
+        treasury_accs[i],
         treasurer,
-        mint_acc,
+        mint_accs[i],
         system_program,
-        splt_program,
+        Self::token_program(mint_accs[i], splt_programs)?,
         sysvar_rent_acc,
         splata_program,
         &[],
       )?;
-      // Deposit token S - src_s->treasury_s_acc (amount : reserve_s)
-      XSPLT::transfer(
-        reserves,
-        src_acc,
-        treasury_acc,
+      // Deposit token - src_acc->treasury_acc, the reserve is what the treasury received
+      reserves[i] = Self::deposit(
+        reserves[i],
+        src_accs[i],
+        mint_accs[i],
+        treasury_accs[i],
         payer,
-        splt_program,
-        &[],
+        splt_programs,
       )?;
     }
     /////finished providing Liquidity/////
 
-    let mint_acc = mint_accs.get(0).ok_or(AppError::MissingAccount)?;
+    // Dust-sized pools are rejected, the locked LPT must leave something for the creator
+    for i in 0..token_count {
+      Self::is_minimum_reserve(reserves[i], minimum_accs[i], mint_accs[i], program_id)?;
+    }
+    // Token 0 also backs the LPT supply, which starts with the locked liquidity
+    if reserves[0] <= MINIMUM_LIQUIDITY {
+      return Err(AppError::ZeroValue.into());
+    }
+
+    let mint_acc = mint_accs[0];
     // Initialize mint
-    let mint_data = Mint::unpack_unchecked(&mint_acc.data.borrow())?;
+    let (mint_data, _) = Mint::unpack_with_extensions(&mint_acc.data.borrow())?;
     XSPLT::initialize_mint(
       mint_data.decimals,
       mint_lpt_acc,
@@ -206,40 +402,59 @@ impl Processor {
       splata_program,
       &[],
     )?;
-    // Mint LPT  mint_lpt_acc->lpt_acc()
+    // Initialize lock account
+    XSPLATA::initialize_account(
+      payer,
+      lock_acc,
+      locker,
+      mint_lpt_acc,
+      system_program,
+      splt_program,
+      sysvar_rent_acc,
+      splata_program,
+      &[],
+    )?;
+    // Mint LPT  mint_lpt_acc->lock_acc (locked forever) and the rest to lpt_acc
     XSPLT::mint_to(
-      reserves[0],
+      MINIMUM_LIQUIDITY,
       mint_lpt_acc,
-      lpt_acc,
+      lock_acc,
       treasurer,
       splt_program,
       seed,
     )?;
-
-    // Initialize vault
-    XSPLT::initialize_account(
-      vault_acc,
-      mint_acc,
+    XSPLT::mint_to(
+      reserves[0] - MINIMUM_LIQUIDITY,
+      mint_lpt_acc,
+      lpt_acc,
       treasurer,
-      sysvar_rent_acc,
       splt_program,
-      &[],
+      seed,
     )?;
 
+    // Initialize vaults, each one collects the earnings charged in its token
+    for i in 0..token_count {
+      XSPLT::initialize_account(
+        vault_accs[i],
+        mint_accs[i],
+        treasurer,
+        sysvar_rent_acc,
+        Self::token_program(mint_accs[i], splt_programs)?,
+        &[],
+      )?;
+    }
+
     // Update pool data
     pool_data.owner = *owner.key;
     pool_data.state = PoolState::Initialized;
     pool_data.mint_lpt = *mint_lpt_acc.key;
-    pool_data.vault = *vault_acc.key; // lp token storage account
+    pool_data.is_earning = true;
 
     for i in 0..token_count {
-      let mint_item_acc = mint_accs.get(i).ok_or(AppError::MissingAccount)?;
-      let treasury_item_acc = treasury_accs.get(i).ok_or(AppError::MissingAccount)?;      
-      let reserve_item = reserves.get(i).ok_or(AppError::MissingAccount)?;
-
-      pool_data.mint[i] = *mint_item_acc.key;
-      pool_data.treasury[i] = *treasury_item_acc.key;
-      pool_data.reserve[i] = reserve_item;
+      pool_data.mints[i] = *mint_accs[i].key;
+      pool_data.treasurys[i] = *treasury_accs[i].key;
+      pool_data.reserves[i] = reserves[i];
+      pool_data.vaults[i] = *vault_accs[i].key;
     }
 
     Pool::pack(pool_data, &mut pool_acc.data.borrow_mut())?;
@@ -249,6 +464,7 @@ impl Processor {
 
   pub fn add_liquidity(
     deltas: Vec<u64>,
+    min_lpt: u64,
     program_id: &Pubkey,
     accounts: &[AccountInfo],
   ) -> ProgramResult {
@@ -260,29 +476,11 @@ impl Processor {
 
     let treasurer = next_account_info(accounts_iter)?;
     let splt_program = next_account_info(accounts_iter)?;
+    let splt_2022_program = next_account_info(accounts_iter)?;
+    let splt_programs = &[splt_program, splt_2022_program];
+    let system_program = next_account_info(accounts_iter)?;
 
-    let mut src_accs = Vec::new();
-    let mut treasury_accs = Vec::new();
-
-    let mut token_count = 0;
-    for accounts in accounts_iter.chunks(2) {
-      if accounts.len() < 2 {
-        break;
-      } else {
-        let src_acc = next_account_info(accounts)?;
-        let treasury_acc = next_account_info(accounts)?;
-    
-        src_accs.push(src_acc);
-        treasury_accs.push(treasury_acc);
-
-        token_count++;
-      }      
-    }
-
-    if token_count > MAX_TOKEN_COUNT || deltas.len() != src_acc.len() || src_acc.len() != treasury_acc.len() {
-      return Err(AppError::ConstructorOnce.into());
-    }
-
+    Self::is_token_programs(splt_program, splt_2022_program)?;
     Self::is_program(program_id, &[pool_acc])?;
     Self::is_signer(&[owner])?;
 
@@ -294,32 +492,93 @@ impl Processor {
       return Err(AppError::InvalidOwner.into());
     }
 
+    // src_acc, mint_acc, treasury_acc for every token of the pool
+    let token_count = pool_data.token_count();
+    if deltas.len() != token_count {
+      return Err(AppError::InvalidInstruction.into());
+    }
+    let mut src_accs = Vec::with_capacity(token_count);
+    let mut mint_accs = Vec::with_capacity(token_count);
+    let mut treasury_accs = Vec::with_capacity(token_count);
     for i in 0..token_count {
-      let treasury_acc = treasury_accs.get(i).ok_or(AppError::MissingAccount)?;
-      if pool_data.treasury[i] != *treasury_acc.key {
+      let src_acc = next_account_info(accounts_iter)?;
+      let mint_acc = next_account_info(accounts_iter)?;
+      let treasury_acc = next_account_info(accounts_iter)?;
+      if pool_data.treasurys[i] != *treasury_acc.key {
         return Err(AppError::InvalidOwner.into());
       }
+      if pool_data.mints[i] != *mint_acc.key {
+        return Err(AppError::InvalidMint.into());
+      }
+      src_accs.push(src_acc);
+      mint_accs.push(mint_acc);
+      treasury_accs.push(treasury_acc);
+    }
+    let native_index = (0..token_count).position(|i| Self::is_native(mint_accs[i], src_accs[i], owner));
+    let native_acc = Self::next_native_account(accounts_iter, native_index.is_some())?;
+
+    if !pool_data.is_active() {
+      return Err(AppError::InactivePool.into());
+    }
+    if pool_data.is_paused(PAUSE_DEPOSIT) {
+      return Err(AppError::PausedOperation.into());
+    }
+    // Deposits would move the LPT per token of the retiring leg
+    if pool_data.retiring().is_some() {
+      return Err(AppError::RetiringToken.into());
     }
 
-    for itemDelta in &deltas {
-      if (itemDelta == 0) {
+    for &delta in &deltas {
+      if delta == 0 {
         return Err(AppError::ZeroValue.into());
       }
     }
 
-    let (lpt, reserves) = Oracle::rake(
-      deltas,
-      pool_data.reserves,
+    // Deposit token, LPT is priced on what the treasuries actually received
+    let mut received_deltas = Vec::with_capacity(token_count);
+    for i in 0..token_count {
+      let received_delta = match native_acc {
+        Some(wsol_acc) if native_index == Some(i) => {
+          Self::deposit_native(
+            deltas[i],
+            src_accs[i],
+            wsol_acc,
+            mint_accs[i],
+            treasury_accs[i],
+            system_program,
+            splt_programs,
+          )?
+        }
+        _ => Self::deposit(
+          deltas[i],
+          src_accs[i],
+          mint_accs[i],
+          treasury_accs[i],
+          owner,
+          splt_programs,
+        )?,
+      };
+      received_deltas.push(received_delta);
+    }
+
+    // Yield-bearing tokens are valued in the underlying through their cached rates
+    let now = Clock::get()?.unix_timestamp;
+    let (lpt, _) = Oracle::rake(
+      Self::scale_amounts(&pool_data, &received_deltas, now, Oracle::scale)?,
+      Self::scale_amounts(&pool_data, &pool_data.reserves[0..token_count], now, Oracle::scale)?,
       mint_lpt_data.supply,
     )
     .ok_or(AppError::Overflow)?;
-
-    // Deposit token
-    for i in 0..token_count {
-      if deltas[i] > 0 {
-        XSPLT::transfer(deltas[i], src_accs[i], treasury_accs[i], owner, splt_program, &[])?;
-        pool_data.reserves[i] = reserves[i];
-      }
+    if lpt == 0 {
+      return Err(AppError::ZeroValue.into());
+    }
+    if lpt < min_lpt {
+      return Err(AppError::ExceedLimit.into());
+    }
+    for (reserve, received_delta) in pool_data.reserves.iter_mut().zip(&received_deltas) {
+      *reserve = reserve
+        .checked_add(*received_delta)
+        .ok_or(AppError::Overflow)?;
     }
 
     // Update pool
@@ -327,229 +586,593 @@ impl Processor {
     // Mint LPT
     XSPLT::mint_to(lpt, mint_lpt_acc, lpt_acc, treasurer, splt_program, seed)?;
 
-        Ok(())
-    }
-
-    pub fn remove_liquidity(
-        lpt: u64,
-        program_id: &Pubkey,
-        accounts: &[AccountInfo],
-    ) -> ProgramResult {
-        let accounts_iter = &mut accounts.iter();
-        let owner = next_account_info(accounts_iter)?;
-        let pool_acc = next_account_info(accounts_iter)?;
-        let lpt_acc = next_account_info(accounts_iter)?;
-        let mint_lpt_acc = next_account_info(accounts_iter)?;
+    Ok(())
+  }
 
-        let dst_s_acc = next_account_info(accounts_iter)?;
-        let treasury_s_acc = next_account_info(accounts_iter)?;
+  pub fn remove_liquidity(
+    lpt: u64,
+    min_amounts: Vec<u64>,
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+  ) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    let owner = next_account_info(accounts_iter)?;
+    let pool_acc = next_account_info(accounts_iter)?;
+    let lpt_acc = next_account_info(accounts_iter)?;
+    let mint_lpt_acc = next_account_info(accounts_iter)?;
 
-        let dst_a_acc = next_account_info(accounts_iter)?;
-        let treasury_a_acc = next_account_info(accounts_iter)?;
+    let treasurer = next_account_info(accounts_iter)?;
+    let splt_program = next_account_info(accounts_iter)?;
+    let splt_2022_program = next_account_info(accounts_iter)?;
+    let splt_programs = &[splt_program, splt_2022_program];
+    let system_program = next_account_info(accounts_iter)?;
+    let splata_program = next_account_info(accounts_iter)?;
 
-        let dst_b_acc = next_account_info(accounts_iter)?;
-        let treasury_b_acc = next_account_info(accounts_iter)?;
+    Self::is_token_programs(splt_program, splt_2022_program)?;
+    Self::is_program(program_id, &[pool_acc])?;
+    Self::is_signer(&[owner])?;
+    let seed: &[&[&[u8]]] = &[&[&Self::safe_seed(pool_acc, treasurer, program_id)?[..]]];
 
-        let treasurer = next_account_info(accounts_iter)?;
-        let splt_program = next_account_info(accounts_iter)?;
+    let mint_lpt_data = Mint::unpack(&mint_lpt_acc.data.borrow())?;
+    let mut pool_data = Pool::unpack(&pool_acc.data.borrow())?;
+    if pool_data.mint_lpt != *mint_lpt_acc.key {
+      return Err(AppError::UnmatchedPool.into());
+    }
 
-        Self::is_program(program_id, &[pool_acc])?;
-        Self::is_signer(&[owner])?;
-        let seed: &[&[&[u8]]] = &[&[&Self::safe_seed(pool_acc, treasurer, program_id)?[..]]];
+    // dst_acc, mint_acc, treasury_acc for every token of the pool
+    let token_count = pool_data.token_count();
+    if min_amounts.len() != token_count {
+      return Err(AppError::InvalidInstruction.into());
+    }
+    let mut dst_accs = Vec::with_capacity(token_count);
+    let mut mint_accs = Vec::with_capacity(token_count);
+    let mut treasury_accs = Vec::with_capacity(token_count);
+    for i in 0..token_count {
+      let dst_acc = next_account_info(accounts_iter)?;
+      let mint_acc = next_account_info(accounts_iter)?;
+      let treasury_acc = next_account_info(accounts_iter)?;
+      if pool_data.treasurys[i] != *treasury_acc.key {
+        return Err(AppError::UnmatchedPool.into());
+      }
+      if pool_data.mints[i] != *mint_acc.key {
+        return Err(AppError::InvalidMint.into());
+      }
+      dst_accs.push(dst_acc);
+      mint_accs.push(mint_acc);
+      treasury_accs.push(treasury_acc);
+    }
+    let native_index = (0..token_count).position(|i| Self::is_native(mint_accs[i], dst_accs[i], owner));
+    let native_acc = Self::next_native_account(accounts_iter, native_index.is_some())?;
 
-        let mint_lpt_data = Mint::unpack(&mint_lpt_acc.data.borrow())?;
-        let mut pool_data = Pool::unpack_from_slice(&pool_acc.data.borrow())?;
-        if pool_data.mint_lpt != *mint_lpt_acc.key
-            || pool_data.treasury_s != *treasury_s_acc.key
-            || pool_data.treasury_a != *treasury_a_acc.key
-            || pool_data.treasury_b != *treasury_b_acc.key
-        {
-            return Err(AppError::UnmatchedPool.into());
-        }
+    if !pool_data.can_withdraw() {
+      return Err(AppError::PausedOperation.into());
+    }
 
-        if pool_data.is_frozen() {
-            return Err(AppError::FrozenPool.into());
-        }
+    if lpt == 0 {
+      return Err(AppError::ZeroValue.into());
+    }
 
-        if lpt == 0 {
-            return Err(AppError::ZeroValue.into());
-        }
+    let mut deltas = Vec::with_capacity(token_count);
+    for (reserve, min_amount) in pool_data.reserves.iter_mut().zip(&min_amounts) {
+      let delta = (lpt as u128)
+        .checked_mul(*reserve as u128)
+        .ok_or(AppError::Overflow)?
+        .checked_div(mint_lpt_data.supply as u128)
+        .ok_or(AppError::Overflow)? as u64;
+      if delta < *min_amount {
+        return Err(AppError::ExceedLimit.into());
+      }
+      *reserve = reserve
+        .checked_sub(delta)
+        .ok_or(AppError::Overflow)?;
+      deltas.push(delta);
+    }
 
-        let delta_s = (lpt as u128)
-            .checked_mul(pool_data.reserve_s as u128)
-            .ok_or(AppError::Overflow)?
-            .checked_div(mint_lpt_data.supply as u128)
-            .ok_or(AppError::Overflow)? as u64;
-        let delta_a = (lpt as u128)
-            .checked_mul(pool_data.reserve_a as u128)
-            .ok_or(AppError::Overflow)?
-            .checked_div(mint_lpt_data.supply as u128)
-            .ok_or(AppError::Overflow)? as u64;
-        let delta_b = (lpt as u128)
-            .checked_mul(pool_data.reserve_b as u128)
-            .ok_or(AppError::Overflow)?
-            .checked_div(mint_lpt_data.supply as u128)
-            .ok_or(AppError::Overflow)? as u64;
-        
-        XSPLT::burn(lpt, lpt_acc, mint_lpt_acc, owner, splt_program, seed)?;
-        
-        pool_data.reserve_s = pool_data
-            .reserve_s
-            .checked_sub(delta_s)
-            .ok_or(AppError::Overflow)?;
-        pool_data.reserve_a = pool_data
-            .reserve_a
-            .checked_sub(delta_a)
-            .ok_or(AppError::Overflow)?;
-        pool_data.reserve_b = pool_data
-            .reserve_b
-            .checked_sub(delta_b)
-            .ok_or(AppError::Overflow)?;
+    XSPLT::burn(
+      lpt,
+      lpt_acc,
+      mint_lpt_acc,
+      owner,
+      Self::token_program(mint_lpt_acc, splt_programs)?,
+      seed,
+    )?;
 
-        if pool_data.reserve_s == 0 {
-            pool_data.state = PoolState::Frozen;
-        }
-        Pool::pack_into_slice(pool_data, &mut pool_acc.data.borrow_mut())?;
-        // Withdraw token
-        XSPLT::transfer(
-            delta_s,
-            treasury_s_acc,
-            dst_s_acc,
-            treasurer,
-            splt_program,
-            seed,
-        )?;
-        XSPLT::transfer(
-            delta_a,
-            treasury_a_acc,
-            dst_a_acc,
+    // A drained pool has no price left, so it can only be wound down.
+    // The retiring leg is the exception, it is priced at its retirement
+    let retiring = pool_data.retiring();
+    if (0..token_count).any(|i| retiring != Some(i) && pool_data.reserves[i] == 0) {
+      pool_data.state = PoolState::Deprecated;
+    }
+    Pool::pack(pool_data, &mut pool_acc.data.borrow_mut())?;
+    // Withdraw token
+    for i in 0..token_count {
+      match native_acc {
+        Some(wsol_acc) if native_index == Some(i) => {
+          Self::withdraw_native(
+            deltas[i],
+            treasury_accs[i],
+            mint_accs[i],
+            dst_accs[i],
+            wsol_acc,
+            owner,
+            system_program,
             treasurer,
-            splt_program,
+            splt_programs,
             seed,
-        )?;
-        XSPLT::transfer(
-            delta_b,
-            treasury_b_acc,
-            dst_b_acc,
+          )?
+        }
+        _ => {
+          Self::ensure_account(
+            dst_accs[i],
+            owner,
+            owner,
+            mint_accs[i],
+            system_program,
+            splt_programs,
+            splata_program,
+          )?;
+          Self::withdraw(
+            deltas[i],
+            treasury_accs[i],
+            mint_accs[i],
+            dst_accs[i],
             treasurer,
-            splt_program,
+            splt_programs,
             seed,
-        )?;
-
-        Ok(())
+          )?
+        }
+      }
     }
 
-    pub fn swap(
-        amount: u64,
-        limit: u64,
-        program_id: &Pubkey,
-        accounts: &[AccountInfo],
-    ) -> ProgramResult {
-        let accounts_iter = &mut accounts.iter();
-        let payer = next_account_info(accounts_iter)?;
-        let pool_acc = next_account_info(accounts_iter)?;
-        let vault_acc = next_account_info(accounts_iter)?;
+    Ok(())
+  }
 
-        let src_acc = next_account_info(accounts_iter)?;
-        let treasury_bid_acc = next_account_info(accounts_iter)?;
+  pub fn swap(
+    amount: u64,
+    limit: u64,
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+  ) -> ProgramResult {
+    // pool_acc and the treasuries sit at SWAP_POOL_INDEX and SWAP_TREASURY_*_INDEX
+    let accounts_iter = &mut accounts.iter();
+    let payer = next_account_info(accounts_iter)?;
+    let pool_acc = next_account_info(accounts_iter)?;
+    let vault_acc = next_account_info(accounts_iter)?; // vault of the ask token
 
-        let dst_acc = next_account_info(accounts_iter)?;
-        let treasury_ask_acc = next_account_info(accounts_iter)?;
+    let src_acc = next_account_info(accounts_iter)?;
+    let mint_bid_acc = next_account_info(accounts_iter)?;
+    let treasury_bid_acc = next_account_info(accounts_iter)?;
 
-        let treasury_sen_acc = next_account_info(accounts_iter)?;
+    let dst_acc = next_account_info(accounts_iter)?;
+    let mint_ask_acc = next_account_info(accounts_iter)?;
+    let treasury_ask_acc = next_account_info(accounts_iter)?;
 
-        let treasurer = next_account_info(accounts_iter)?;
-        let splt_program = next_account_info(accounts_iter)?;
+    let treasurer = next_account_info(accounts_iter)?;
+    let splt_program = next_account_info(accounts_iter)?;
+    let splt_2022_program = next_account_info(accounts_iter)?;
+    let splt_programs = &[splt_program, splt_2022_program];
+    let system_program = next_account_info(accounts_iter)?;
+    let splata_program = next_account_info(accounts_iter)?;
+    let is_native_bid = Self::is_native(mint_bid_acc, src_acc, payer);
+    let is_native_ask = Self::is_native(mint_ask_acc, dst_acc, payer);
+    let native_acc = Self::next_native_account(accounts_iter, is_native_bid || is_native_ask)?;
+    let allowlist_acc = Self::next_optional_account_info(accounts_iter, program_id);
+    let referrer_acc = Self::next_optional_account_info(accounts_iter, program_id); // token account of the ask mint
+    let volume_acc = Self::next_optional_account_info(accounts_iter, program_id);
+    let discount_acc = Self::next_optional_account_info(accounts_iter, program_id); // token account of the discount mint
+    let instructions_acc = Self::next_optional_account_info(accounts_iter, program_id);
+    let registry_acc = Self::next_optional_account_info(accounts_iter, program_id);
+    let price_acc = Self::next_optional_account_info(accounts_iter, program_id);
+
+    Self::is_token_programs(splt_program, splt_2022_program)?;
+    Self::is_program(program_id, &[pool_acc])?;
+    Self::is_signer(&[payer])?;
 
-        Self::is_program(program_id, &[pool_acc])?;
-        Self::is_signer(&[payer])?;
+    let mut pool_data = Pool::unpack(&pool_acc.data.borrow())?;
+    let seed: &[&[&[u8]]] = &[&[&Self::safe_seed(pool_acc, treasurer, program_id)?[..]]];
+    let (bid_code, bid_reserve) = pool_data
+      .get_reserve(treasury_bid_acc.key)
+      .ok_or(AppError::UnmatchedPool)?;
+    let (ask_code, ask_reserve) = pool_data
+      .get_reserve(treasury_ask_acc.key)
+      .ok_or(AppError::UnmatchedPool)?;
+    if pool_data.vaults[ask_code as usize] != *vault_acc.key {
+      return Err(AppError::UnmatchedPool.into());
+    }
+    if pool_data.mints[bid_code as usize] != *mint_bid_acc.key
+      || pool_data.mints[ask_code as usize] != *mint_ask_acc.key
+    {
+      return Err(AppError::InvalidMint.into());
+    }
 
-        let mut pool_data = Pool::unpack_from_slice(&pool_acc.data.borrow())?;
-        let seed: &[&[&[u8]]] = &[&[&Self::safe_seed(pool_acc, treasurer, program_id)?[..]]];
-        let (bid_code, bid_reserve) = pool_data
-            .get_reserve(treasury_bid_acc.key)
-            .ok_or(AppError::UnmatchedPool)?;
-        let (ask_code, ask_reserve) = pool_data
-            .get_reserve(treasury_ask_acc.key)
-            .ok_or(AppError::UnmatchedPool)?;
-        let (sen_code, _) = pool_data
-            .get_reserve(treasury_sen_acc.key)
-            .ok_or(AppError::UnmatchedPool)?;
-        if sen_code != 0 {
-          return Err(AppError::UnmatchedPool.into());
-        }
+    if !pool_data.is_active() {
+      return Err(AppError::InactivePool.into());
+    }
+    if pool_data.is_paused(PAUSE_SWAP) {
+      return Err(AppError::PausedOperation.into());
+    }
+    if pool_data
+      .retiring()
+      .is_some_and(|index| index == bid_code as usize || index == ask_code as usize)
+    {
+      return Err(AppError::RetiringToken.into());
+    }
+    if amount == 0 {
+      return Err(AppError::ZeroValue.into());
+    }
+    if *treasury_bid_acc.key == *treasury_ask_acc.key {
+      return Ok(());
+    }
 
-        if pool_data.is_frozen() {
-          return Err(AppError::FrozenPool.into());
-        }
-        if amount == 0 {
-           return Err(AppError::ZeroValue.into());
-        }
-        if *treasury_bid_acc.key == *treasury_ask_acc.key {
-          return Ok(());
-        }
+    // Listed traders pay the allowlist earning
+    let mut earning_rate = EARNING;
+    let mut is_allowlisted = false;
+    if let Some(allowlist_acc) = allowlist_acc {
+      Self::is_program(program_id, &[allowlist_acc])?;
+      let allowlist_data = Allowlist::unpack(&allowlist_acc.data.borrow())?;
+      if allowlist_data.pool != *pool_acc.key {
+        return Err(AppError::UnmatchedPool.into());
+      }
+      if allowlist_data.contains(payer.key) {
+        earning_rate = allowlist_data.earning;
+        is_allowlisted = true;
+      }
+    }
+    // A trader can't refer themselves and rebate the referral share
+    if let Some(referrer_acc) = referrer_acc {
+      if Self::token_account(referrer_acc)?.owner == *payer.key {
+        return Err(AppError::SelfReferral.into());
+      }
+    }
+    // Holders of the discount mint pay less earning
+    if let Some(discount_acc) = discount_acc {
+      let balance = Self::holder_balance(discount_acc, payer, &pool_data.discount_mint)?;
+      earning_rate = Oracle::discounted(earning_rate, pool_data.holder_discount(balance))
+        .ok_or(AppError::Overflow)?;
+    }
+    if !pool_data.is_earning {
+      earning_rate = 0;
+    }
 
-        let new_bid_reserve = bid_reserve.checked_add(amount).ok_or(AppError::Overflow)?;
-        let (new_ask_reserve, paid_amount, earning) =
-        Oracle::curve_in_fee(new_bid_reserve, bid_reserve, ask_reserve, ask_code == 0)
-            .ok_or(AppError::Overflow)?;
-        if paid_amount < limit {
-           return Err(AppError::ExceedLimit.into());
-        }
+    // The fee tier follows the notional the trader swapped over the rolling window before this swap
+    let day = (Clock::get()?.unix_timestamp / SECONDS_PER_DAY) as u64;
+    let mut volume = None;
+    if let Some(volume_acc) = volume_acc {
+      Self::is_program(program_id, &[volume_acc])?;
+      let volume_data = TraderVolume::unpack(&volume_acc.data.borrow())?;
+      if volume_data.pool != *pool_acc.key || volume_data.trader != *payer.key {
+        return Err(AppError::UnmatchedPool.into());
+      }
+      volume = Some((volume_acc, volume_data));
+    }
+    let fee_discount = match volume {
+      Some((_, volume_data)) => pool_data.fee_discount(volume_data.total(day)),
+      None => 0,
+    };
 
-        XSPLT::transfer(amount, src_acc, treasury_bid_acc, payer, splt_program, &[])?;
-        match bid_code {
-            0 => pool_data.reserve_s = new_bid_reserve,
-            1 => pool_data.reserve_a = new_bid_reserve,
-            2 => pool_data.reserve_b = new_bid_reserve,
-            _ => return Err(AppError::UnmatchedPool.into()),
-        }
-        match ask_code {
-            0 => pool_data.reserve_s = new_ask_reserve,
-            1 => pool_data.reserve_a = new_ask_reserve,
-            2 => pool_data.reserve_b = new_ask_reserve,
-            _ => return Err(AppError::UnmatchedPool.into()),
+    // Anti-sandwich, unless the trader is listed or the swap comes through a registered router
+    if pool_data.is_anti_sandwich && !is_allowlisted {
+      let instructions_acc = instructions_acc.ok_or(ProgramError::NotEnoughAccountKeys)?;
+      let current_index = load_current_index_checked(instructions_acc)?;
+      let current_ix = load_instruction_at_checked(current_index as usize, instructions_acc)?;
+      let mut is_routed = false;
+      if let Some(registry_acc) = registry_acc {
+        Self::is_program(program_id, &[registry_acc])?;
+        let registry_data = RouterRegistry::unpack(&registry_acc.data.borrow())?;
+        if registry_data.pool != *pool_acc.key {
+          return Err(AppError::UnmatchedPool.into());
         }
-        XSPLT::transfer(
-            paid_amount,
-            treasury_ask_acc,
-            dst_acc,
-            treasurer,
-            splt_program,
-            seed,
-        )?;
+        is_routed = registry_data.contains(&current_ix.program_id);
+      }
+      if !is_routed
+        && Self::has_opposite_swap(
+          pool_acc,
+          treasury_bid_acc,
+          treasury_ask_acc,
+          instructions_acc,
+          current_index as usize,
+          program_id,
+        )?
+      {
+        return Err(AppError::SandwichDetected.into());
+      }
+    }
 
-        if earning != 0 {
-        let new_ask_reserve_with_earning = new_ask_reserve
-            .checked_add(earning)
+    let slot = Clock::get()?.slot;
+
+    // Yield-bearing tokens are compared through their cached rates, which must be fresh
+    let now = Clock::get()?.unix_timestamp;
+    let bid_rate = pool_data
+      .get_rate(bid_code as usize, now)
+      .ok_or(AppError::ExpiredRate)?;
+    let ask_rate = pool_data
+      .get_rate(ask_code as usize, now)
+      .ok_or(AppError::ExpiredRate)?;
+
+    // Swaps between token 0 and token 1 of a pegged pool are quoted off the price account,
+    // and fall back to the curve while the price is stale or too uncertain.
+    // The feed only prices that pair, so swaps involving any other token always run on the curve
+    let mut pegged_price = None;
+    if pool_data.is_pegged() && bid_code + ask_code == 1 {
+      let price_acc = price_acc.ok_or(ProgramError::NotEnoughAccountKeys)?;
+      if *price_acc.key != pool_data.price_acc {
+        return Err(AppError::UnmatchedPool.into());
+      }
+      let mut price_data = Price::unpack(&price_acc.data.borrow())?;
+      if price_data.is_usable(slot, pool_data.max_staleness, pool_data.max_confidence) {
+        // The feed prices whole tokens, the swap moves the smallest units of each mint
+        let (mint_0_acc, mint_1_acc) = if bid_code == 0 {
+          (mint_bid_acc, mint_ask_acc)
+        } else {
+          (mint_ask_acc, mint_bid_acc)
+        };
+        let (mint_0_data, _) = Mint::unpack_with_extensions(&mint_0_acc.data.borrow())?;
+        let (mint_1_data, _) = Mint::unpack_with_extensions(&mint_1_acc.data.borrow())?;
+        price_data.expo =
+          Oracle::peg_expo(price_data.expo, mint_0_data.decimals, mint_1_data.decimals)
             .ok_or(AppError::Overflow)?;
-        let (new_sen_reserve, earning_in_sen, _) = Oracle::curve_in_fee(
-            new_ask_reserve_with_earning, 
-            new_ask_reserve,              
-            pool_data.reserve_s,
-            true,
-        )
+        pegged_price = Some(price_data);
+      } else {
+        msg!("Price unusable, falling back to the curve");
+      }
+    }
+
+    // Deposit token, the curve runs on what the treasury actually received
+    let bid_amount = match native_acc {
+      Some(wsol_acc) if is_native_bid => Self::deposit_native(
+        amount,
+        src_acc,
+        wsol_acc,
+        mint_bid_acc,
+        treasury_bid_acc,
+        system_program,
+        splt_programs,
+      )?,
+      _ => Self::deposit(
+        amount,
+        src_acc,
+        mint_bid_acc,
+        treasury_bid_acc,
+        payer,
+        splt_programs,
+      )?,
+    };
+    let new_bid_reserve = bid_reserve.checked_add(bid_amount).ok_or(AppError::Overflow)?;
+
+    // The constant-sum curve pays 1:1 until the ask reserve runs dry
+    if pegged_price.is_none()
+      && pool_data.curve == Curve::ConstantSum
+      && Oracle::scale(bid_amount, bid_rate).ok_or(AppError::Overflow)?
+        >= Oracle::scale(ask_reserve, ask_rate).ok_or(AppError::Overflow)?
+    {
+      return Err(AppError::ExceedReserve.into());
+    }
+
+    // The curve runs on the reserves scaled into the underlying, where it executes at
+    // scaled_ask_amount / scaled_bid_amount before fees
+    let scaled_bid_reserve = Oracle::scale(bid_reserve, bid_rate).ok_or(AppError::Overflow)?;
+    let scaled_ask_reserve = Oracle::scale(ask_reserve, ask_rate).ok_or(AppError::Overflow)?;
+    let scaled_new_bid_reserve = Oracle::scale(new_bid_reserve, bid_rate).ok_or(AppError::Overflow)?;
+    let scaled_bid_amount = scaled_new_bid_reserve
+      .checked_sub(scaled_bid_reserve)
+      .ok_or(AppError::Overflow)?;
+    let scaled_ask_amount = match pegged_price {
+      Some(_) => 0,
+      None => Oracle::curve_by(
+        pool_data.curve,
+        scaled_new_bid_reserve,
+        scaled_bid_reserve,
+        scaled_ask_reserve,
+      )
+      .and_then(|scaled_new_ask_reserve| scaled_ask_reserve.checked_sub(scaled_new_ask_reserve))
+      .ok_or(AppError::Overflow)?,
+    };
+
+    let scaled_new_ask_reserve = scaled_ask_reserve
+      .checked_sub(scaled_ask_amount)
+      .ok_or(AppError::Overflow)?;
+
+    // Circuit breaker, the spot price after the swap may only drift max_deviation from the spot
+    // price the slot started with. The constant-sum curve holds its spot price at 1:1, so there
+    // the executed price is checked against 1:1. Pegged swaps follow the price account and
+    // can't move the pool price, so they aren't checked
+    pool_data.update_reference(slot);
+    if pool_data.max_deviation != 0 && pegged_price.is_none() {
+      let deviation = match pool_data.curve {
+        Curve::Product => Oracle::price_change(
+          scaled_new_bid_reserve,
+          scaled_new_ask_reserve,
+          Oracle::scale(pool_data.reference_reserves[bid_code as usize], bid_rate)
+            .ok_or(AppError::Overflow)?,
+          Oracle::scale(pool_data.reference_reserves[ask_code as usize], ask_rate)
+            .ok_or(AppError::Overflow)?,
+        ),
+        Curve::ConstantSum => Oracle::price_change(scaled_bid_amount, scaled_ask_amount, 1, 1),
+      }
+      .ok_or(AppError::Overflow)?;
+      if deviation > Oracle::bps_to_rate(pool_data.max_deviation) {
+        return Err(AppError::PriceDeviation.into());
+      }
+    }
+
+    // The dynamic fee grows with the decayed volatility of the spot prices from slot to slot,
+    // and separately with trades that unbalance the pair in the underlying.
+    // Pegged swaps pay the spread instead
+    let mut base_fee = FEE;
+    if pool_data.is_dynamic_fee && pegged_price.is_none() {
+      // The first swap of a slot adds how far the spot prices moved over the last slot
+      if pool_data.last_slot != slot {
+        let volatility =
+          Oracle::decay_volatility(pool_data.volatility, slot.saturating_sub(pool_data.last_slot))
+            .ok_or(AppError::Overflow)?;
+        let token_count = pool_data.token_count();
+        let spot_change = match pool_data.curve {
+          Curve::Product => Oracle::spot_change(
+            &pool_data.reserves[0..token_count],
+            &pool_data.last_reserves[0..token_count],
+          )
+          .ok_or(AppError::Overflow)?,
+          // The constant sum holds the spot prices at 1:1
+          Curve::ConstantSum => 0,
+        };
+        pool_data.volatility = volatility.saturating_add(spot_change);
+        pool_data.last_reserves = pool_data.reserves.clone();
+        pool_data.last_slot = slot;
+      }
+      let imbalance =
+        Oracle::imbalance(scaled_bid_reserve, scaled_ask_reserve).ok_or(AppError::Overflow)?;
+      let new_imbalance = Oracle::imbalance(scaled_new_bid_reserve, scaled_new_ask_reserve)
         .ok_or(AppError::Overflow)?;
-        match ask_code {
-            1 => pool_data.reserve_a = new_ask_reserve_with_earning,
-            2 => pool_data.reserve_b = new_ask_reserve_with_earning,
-            _ => return Err(AppError::UnmatchedPool.into()),
-        }
-        pool_data.reserve_s = new_sen_reserve;
-        XSPLT::transfer(
-            earning_in_sen,
-            treasury_sen_acc,
-            vault_acc,
+      base_fee = Oracle::dynamic_fee(
+        pool_data.volatility,
+        new_imbalance.saturating_sub(imbalance),
+        pool_data.min_fee,
+        pool_data.max_fee,
+      )
+      .ok_or(AppError::Overflow)?;
+    }
+    let fee_rate = Oracle::discounted(base_fee, fee_discount).ok_or(AppError::Overflow)?;
+    let (new_ask_reserve, paid_amount, earning) = match pegged_price {
+      Some(price_data) => u64::try_from(price_data.price).ok().and_then(|price| {
+        Oracle::peg_in_fee(
+          bid_amount,
+          ask_reserve,
+          price,
+          price_data.expo,
+          bid_code == 1,
+          Oracle::bps_to_rate(pool_data.spread),
+          earning_rate,
+        )
+      }),
+      None => Oracle::curve_in_fee_with_rates(
+        pool_data.curve,
+        new_bid_reserve,
+        bid_reserve,
+        ask_reserve,
+        bid_rate,
+        ask_rate,
+        fee_rate,
+        earning_rate,
+      ),
+    }
+    .ok_or(AppError::Overflow)?;
+    if paid_amount < limit {
+      return Err(AppError::ExceedLimit.into());
+    }
+    // The traded notional in token 0, off the price account for pegged swaps and at the spot
+    // price of the scaled reserves otherwise
+    let notional = match pegged_price {
+      _ if bid_code == 0 => Some(bid_amount),
+      Some(price_data) => u64::try_from(price_data.price).ok().and_then(|price| {
+        Oracle::peg_in_fee(bid_amount, u64::MAX, price, price_data.expo, true, 0, 0)
+          .map(|(_, quote, _)| quote)
+      }),
+      None => pool_data.get_rate(0, now).and_then(|rate_0| {
+        Oracle::scale(pool_data.reserves[0], rate_0)
+          .and_then(|scaled_reserve_0| {
+            Oracle::value_in_reserve_0(
+              pool_data.curve,
+              scaled_bid_amount,
+              scaled_bid_reserve,
+              scaled_reserve_0,
+            )
+          })
+          .and_then(|scaled_value| Oracle::unscale(scaled_value, rate_0))
+      }),
+    }
+    .ok_or(AppError::Overflow)?;
+    // Trade size caps, relative to the reserves before the swap
+    if pool_data.max_bid_ratio != 0
+      && bid_amount > Oracle::bps_of(bid_reserve, pool_data.max_bid_ratio).ok_or(AppError::Overflow)?
+    {
+      return Err(AppError::TradeTooLarge.into());
+    }
+    if pool_data.max_ask_ratio != 0
+      && paid_amount > Oracle::bps_of(ask_reserve, pool_data.max_ask_ratio).ok_or(AppError::Overflow)?
+    {
+      return Err(AppError::TradeTooLarge.into());
+    }
+
+    pool_data.reserves[bid_code as usize] = new_bid_reserve;
+    pool_data.reserves[ask_code as usize] = new_ask_reserve;
+    match native_acc {
+      Some(wsol_acc) if is_native_ask => Self::withdraw_native(
+        paid_amount,
+        treasury_ask_acc,
+        mint_ask_acc,
+        dst_acc,
+        wsol_acc,
+        payer,
+        system_program,
+        treasurer,
+        splt_programs,
+        seed,
+      )?,
+      _ => {
+        Self::ensure_account(
+          dst_acc,
+          payer,
+          payer,
+          mint_ask_acc,
+          system_program,
+          splt_programs,
+          splata_program,
+        )?;
+        Self::withdraw(
+          paid_amount,
+          treasury_ask_acc,
+          mint_ask_acc,
+          dst_acc,
+          treasurer,
+          splt_programs,
+          seed,
+        )?
+      }
+    }
+
+    // The earning stays in the ask token, it can be converted later from the vault
+    if earning != 0 {
+      // The referrer takes its share of the earning, the vault keeps the rest
+      let (vault_earning, referral) = match referrer_acc {
+        Some(_) => Oracle::split_earning(earning, pool_data.referral_share)
+          .ok_or(AppError::Overflow)?,
+        None => (earning, 0),
+      };
+      Self::withdraw(
+        vault_earning,
+        treasury_ask_acc,
+        mint_ask_acc,
+        vault_acc,
+        treasurer,
+        splt_programs,
+        seed,
+      )?;
+      if let Some(referrer_acc) = referrer_acc {
+        if referral != 0 {
+          Self::withdraw(
+            referral,
+            treasury_ask_acc,
+            mint_ask_acc,
+            referrer_acc,
             treasurer,
-            splt_program,
+            splt_programs,
             seed,
-        )?;
+          )?;
+          msg!("Referral of {} paid to {}", referral, referrer_acc.key);
         }
+      }
+    }
 
-        Pool::pack_into_slice(pool_data, &mut pool_acc.data.borrow_mut())?;
+    Pool::pack(pool_data, &mut pool_acc.data.borrow_mut())?;
+    // Record the notional in token 0. Listed traders may trade for free, so their swaps don't
+    // count, and the trader can't be their own referrer, so wash trades pay the full fee
+    if let Some((volume_acc, mut volume_data)) = volume.filter(|_| !is_allowlisted) {
+      volume_data.record(day, notional);
+      TraderVolume::pack(volume_data, &mut volume_acc.data.borrow_mut())?;
+    }
 
-        Ok(())
-    } 
+    Ok(())
+  }
 
     pub fn freeze_pool(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
         let accounts_iter = &mut accounts.iter();
@@ -557,11 +1180,10 @@ impl Processor {
         let pool_acc = next_account_info(accounts_iter)?;
 
         Self::is_program(program_id, &[pool_acc])?;
-        Self::is_signer(&[owner])?;
-        Self::is_pool_owner(owner, pool_acc)?;
+        Self::is_pool_owner(owner, pool_acc, program_id, accounts_iter.as_slice())?;
 
         let mut pool_data = Pool::unpack(&pool_acc.data.borrow())?;
-        pool_data.state = PoolState::Frozen;
+        pool_data.pause |= PAUSE_FROZEN;
         Pool::pack(pool_data, &mut pool_acc.data.borrow_mut())?;
 
         Ok(())
@@ -573,11 +1195,10 @@ impl Processor {
         let pool_acc = next_account_info(accounts_iter)?;
 
         Self::is_program(program_id, &[pool_acc])?;
-        Self::is_signer(&[owner])?;
-        Self::is_pool_owner(owner, pool_acc)?;
+        Self::is_pool_owner(owner, pool_acc, program_id, accounts_iter.as_slice())?;
 
         let mut pool_data = Pool::unpack(&pool_acc.data.borrow())?;
-        pool_data.state = PoolState::Initialized;
+        pool_data.pause &= !PAUSE_FROZEN;
         Pool::pack(pool_data, &mut pool_acc.data.borrow_mut())?;
 
         Ok(())
@@ -588,25 +1209,30 @@ impl Processor {
         let owner = next_account_info(accounts_iter)?;
         let pool_acc = next_account_info(accounts_iter)?;
         let vault_acc = next_account_info(accounts_iter)?;
+        let mint_acc = next_account_info(accounts_iter)?;
         let dst_acc = next_account_info(accounts_iter)?;
         let treasurer = next_account_info(accounts_iter)?;
         let splt_program = next_account_info(accounts_iter)?;
+        let splt_2022_program = next_account_info(accounts_iter)?;
+        let splt_programs = &[splt_program, splt_2022_program];
 
+        Self::is_token_programs(splt_program, splt_2022_program)?;
         Self::is_program(program_id, &[pool_acc])?;
-        Self::is_signer(&[owner])?;
-        Self::is_pool_owner(owner, pool_acc)?;
+        Self::is_pool_owner(owner, pool_acc, program_id, accounts_iter.as_slice())?;
 
         let pool_data = Pool::unpack(&pool_acc.data.borrow())?;
         let seed: &[&[&[u8]]] = &[&[&Self::safe_seed(pool_acc, treasurer, program_id)?[..]]];
-        if pool_data.vault != *vault_acc.key {
-            return Err(AppError::InvalidOwner.into());
+        // Any of the token vaults, each one holds the earnings charged in its mint
+        let code = pool_data.get_vault(vault_acc.key).ok_or(AppError::InvalidOwner)?;
+        if pool_data.mints[code as usize] != *mint_acc.key {
+            return Err(AppError::InvalidMint.into());
         }
 
         if amount == 0 {
             return Err(AppError::ZeroValue.into());
         }
         
-        XSPLT::transfer(amount, vault_acc, dst_acc, treasurer, splt_program, seed)?;
+        Self::withdraw(amount, vault_acc, mint_acc, dst_acc, treasurer, splt_programs, seed)?;
 
         Ok(())
     }
@@ -618,8 +1244,7 @@ impl Processor {
     let new_owner = next_account_info(accounts_iter)?;
 
     Self::is_program(program_id, &[pool_acc])?;
-    Self::is_signer(&[owner])?;
-    Self::is_pool_owner(owner, pool_acc)?;
+    Self::is_pool_owner(owner, pool_acc, program_id, accounts_iter.as_slice())?;
 
     // Update pool data
     let mut pool_data = Pool::unpack(&pool_acc.data.borrow())?;
@@ -629,63 +1254,2149 @@ impl Processor {
     Ok(())
   }
 
-  ///
-  /// Utilities
-  ///
-  pub fn is_program(program_id: &Pubkey, accounts: &[&AccountInfo]) -> ProgramResult {
-    for acc in &mut accounts.iter() {
-      if acc.owner != program_id {
-        return Err(AppError::IncorrectProgramId.into());
-      }
+  pub fn toggle_pause(flags: u8, program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    let owner = next_account_info(accounts_iter)?;
+    let pool_acc = next_account_info(accounts_iter)?;
+
+    Self::is_program(program_id, &[pool_acc])?;
+    Self::is_pool_owner(owner, pool_acc, program_id, accounts_iter.as_slice())?;
+
+    if flags == 0 || flags & !PAUSE_ALL != 0 {
+      return Err(AppError::InvalidPauseFlags.into());
     }
+
+    // Update pool data
+    let mut pool_data = Pool::unpack(&pool_acc.data.borrow())?;
+    pool_data.pause ^= flags;
+    Pool::pack(pool_data, &mut pool_acc.data.borrow_mut())?;
+
     Ok(())
   }
 
-  pub fn is_signer(accounts: &[&AccountInfo]) -> ProgramResult {
-    for acc in &mut accounts.iter() {
-      if !acc.is_signer {
-        return Err(AppError::InvalidOwner.into());
+  pub fn update_pool_state(state: u8, program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    let owner = next_account_info(accounts_iter)?;
+    let pool_acc = next_account_info(accounts_iter)?;
+
+    Self::is_program(program_id, &[pool_acc])?;
+    Self::is_pool_owner(owner, pool_acc, program_id, accounts_iter.as_slice())?;
+
+    let state = PoolState::try_from_primitive(state).or(Err(AppError::InvalidInstruction))?;
+    let mut pool_data = Pool::unpack(&pool_acc.data.borrow())?;
+    if !pool_data.can_transit(state) {
+      return Err(AppError::InvalidStateTransition.into());
+    }
+
+    // Update pool data
+    pool_data.state = state;
+    Pool::pack(pool_data, &mut pool_acc.data.borrow_mut())?;
+
+    Ok(())
+  }
+
+  pub fn initialize_multisig(m: u8, program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    let payer = next_account_info(accounts_iter)?;
+    let multisig_acc = next_account_info(accounts_iter)?;
+    let signer_accs = accounts_iter.as_slice(); // multisig members
+
+    Self::is_program(program_id, &[multisig_acc])?;
+    Self::is_signer(&[payer, multisig_acc])?;
+
+    let mut multisig_data = Multisig::unpack_unchecked(&multisig_acc.data.borrow())?;
+    if multisig_data.is_initialized() {
+      return Err(AppError::ConstructorOnce.into());
+    }
+
+    let n = signer_accs.len();
+    if !(MIN_SIGNERS..=MAX_SIGNERS).contains(&n) || !(MIN_SIGNERS..=n).contains(&(m as usize)) {
+      return Err(AppError::InvalidMultisig.into());
+    }
+    for (i, signer_acc) in signer_accs.iter().enumerate() {
+      if multisig_data.signers[0..i].contains(signer_acc.key) {
+        return Err(AppError::InvalidMultisig.into());
       }
+      multisig_data.signers[i] = *signer_acc.key;
     }
+
+    // Update multisig data
+    multisig_data.m = m;
+    multisig_data.n = n as u8;
+    multisig_data.is_initialized = true;
+    Multisig::pack(multisig_data, &mut multisig_acc.data.borrow_mut())?;
+
     Ok(())
   }
 
-    pub fn is_pool_owner(owner: &AccountInfo, pool_acc: &AccountInfo) -> ProgramResult {
-        let pool_data = Pool::unpack(&pool_acc.data.borrow())?;
-        if pool_data.owner != *owner.key {
-          return Err(AppError::InvalidOwner.into());
-        }
+  pub fn add_pool_token(
+    amount: u64,
+    value: u64,
+    min_lpt: u64,
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+  ) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    let payer = next_account_info(accounts_iter)?;
+    let owner = next_account_info(accounts_iter)?;
+    let pool_acc = next_account_info(accounts_iter)?;
+    let lpt_acc = next_account_info(accounts_iter)?;
+    let mint_lpt_acc = next_account_info(accounts_iter)?;
 
-        Ok(())
+    let src_acc = next_account_info(accounts_iter)?;
+    let mint_acc = next_account_info(accounts_iter)?;
+    let treasury_acc = next_account_info(accounts_iter)?;
+    let vault_acc = next_account_info(accounts_iter)?; //owned by treasurer
+    let minimum_acc = next_account_info(accounts_iter)?; // derived from the mint, may not exist
+
+    let treasurer = next_account_info(accounts_iter)?;
+    let system_program = next_account_info(accounts_iter)?;
+    let splt_program = next_account_info(accounts_iter)?;
+    let splt_2022_program = next_account_info(accounts_iter)?;
+    let splt_programs = &[splt_program, splt_2022_program];
+    let sysvar_rent_acc = next_account_info(accounts_iter)?;
+    let splata_program = next_account_info(accounts_iter)?;
+
+    Self::is_token_programs(splt_program, splt_2022_program)?;
+    Self::is_program(program_id, &[pool_acc])?;
+    Self::is_signer(&[payer, vault_acc])?;
+    Self::is_pool_owner(owner, pool_acc, program_id, accounts_iter.as_slice())?;
+
+    let mint_lpt_data = Mint::unpack(&mint_lpt_acc.data.borrow())?;
+    let mut pool_data = Pool::unpack(&pool_acc.data.borrow())?;
+    let seed: &[&[&[u8]]] = &[&[&Self::safe_seed(pool_acc, treasurer, program_id)?[..]]];
+    if pool_data.mint_lpt != *mint_lpt_acc.key {
+      return Err(AppError::UnmatchedPool.into());
+    }
+    if pool_data.mints.contains(mint_acc.key) {
+      return Err(AppError::InvalidMint.into());
     }
 
-    pub fn safe_seed(
-        seed_acc: &AccountInfo,
-        expected_acc: &AccountInfo,
-        program_id: &Pubkey,
-    ) -> Result<[u8; 32], PubkeyError> {
-        let seed: [u8; 32] = seed_acc.key.to_bytes();
-        let key = Pubkey::create_program_address(&[&seed], program_id)?;
-        if key != *expected_acc.key {
-          return Err(PubkeyError::InvalidSeeds);
-        }
-        Ok(seed)
+    if !pool_data.is_active() {
+      return Err(AppError::InactivePool.into());
+    }
+    if pool_data.is_paused(PAUSE_DEPOSIT) {
+      return Err(AppError::PausedOperation.into());
+    }
+    // The LPT per token of the retiring leg holds only while the token set stands
+    if pool_data.retiring().is_some() {
+      return Err(AppError::RetiringToken.into());
     }
 
-  pub fn key_xor(
-    left_key: &Pubkey,
-    right_key: &Pubkey,
-  ) -> Result<Pubkey, PubkeyError> {
-    let left_key_bytes: [u8; 32] = left_key.to_bytes();
-    let right_key_bytes: [u8; 32] = right_key.to_bytes();
-    let mut xor_key_bytes: [u8; 32] = [0; 32];   
+    let token_count = pool_data.token_count();
+    if token_count >= MAX_TOKEN_COUNT {
+      return Err(AppError::ExceedLimit.into());
+    }
+    if amount == 0 || value == 0 {
+      return Err(AppError::ZeroValue.into());
+    }
 
-    for i in 0..32 {
-      xor_key_bytes[i] = left_key_bytes[i] ^ right_key_bytes[i];
+    // Initialize treasury_acc
+    XSPLATA::initialize_account(
+      payer,
+      treasury_acc,
+      treasurer,
+      mint_acc,
+      system_program,
+      Self::token_program(mint_acc, splt_programs)?,
+      sysvar_rent_acc,
+      splata_program,
+      &[],
+    )?;
+    // Initialize vault_acc
+    XSPLT::initialize_account(
+      vault_acc,
+      mint_acc,
+      treasurer,
+      sysvar_rent_acc,
+      Self::token_program(mint_acc, splt_programs)?,
+      &[],
+    )?;
+    // Deposit token - src_acc->treasury_acc, the reserve is what the treasury received
+    let reserve = Self::deposit(amount, src_acc, mint_acc, treasury_acc, payer, splt_programs)?;
+    // Dust-sized legs are rejected like at the pool creation
+    Self::is_minimum_reserve(reserve, minimum_acc, mint_acc, program_id)?;
+
+    // The caller quotes the amount in token 0, and the LPT is what that value is worth against
+    // the existing legs, in the underlying through the cached rates. A leg can't count for more
+    // than the curve will trade it at: one leg of reserve 0 on the product curve, and 1:1 on the
+    // constant sum until the new token gets a rate provider
+    let now = Clock::get()?.unix_timestamp;
+    let scaled_reserves =
+      Self::scale_amounts(&pool_data, &pool_data.reserves[0..token_count], now, Oracle::scale)?;
+    let received_value = (value as u128)
+      .checked_mul(reserve as u128)
+      .ok_or(AppError::Overflow)?
+      .checked_div(amount as u128)
+      .ok_or(AppError::Overflow)? as u64;
+    let quoted_value = Self::scale_amounts(&pool_data, &[received_value], now, Oracle::scale)?[0];
+    let (max_value, total_value) = match pool_data.curve {
+      Curve::Product => (
+        scaled_reserves[0],
+        scaled_reserves[0].checked_mul(token_count as u64),
+      ),
+      Curve::ConstantSum => (
+        reserve,
+        scaled_reserves.iter().try_fold(0u64, |sum, &scaled| sum.checked_add(scaled)),
+      ),
+    };
+    let scaled_value = quoted_value.min(max_value);
+    let total_value = total_value.ok_or(AppError::Overflow)?;
+    let lpt = (mint_lpt_data.supply as u128)
+      .checked_mul(scaled_value as u128)
+      .ok_or(AppError::Overflow)?
+      .checked_div(total_value as u128)
+      .ok_or(AppError::Overflow)? as u64;
+    if lpt == 0 {
+      return Err(AppError::ZeroValue.into());
+    }
+    if lpt < min_lpt {
+      return Err(AppError::ExceedLimit.into());
     }
 
-    let xor_key = Pubkey::new_from_array(xor_key_bytes);
-    
-    Ok(xor_key)
+    // Update pool data
+    pool_data.mints[token_count] = *mint_acc.key;
+    pool_data.treasurys[token_count] = *treasury_acc.key;
+    pool_data.reserves[token_count] = reserve;
+    pool_data.vaults[token_count] = *vault_acc.key;
+    pool_data.reference_slot = 0; // the next swap retakes the reference with the new token
+    Pool::pack(pool_data, &mut pool_acc.data.borrow_mut())?;
+    // Mint LPT
+    XSPLT::mint_to(lpt, mint_lpt_acc, lpt_acc, treasurer, splt_program, seed)?;
+
+    Ok(())
+  }
+
+  pub fn remove_pool_token(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    let owner = next_account_info(accounts_iter)?;
+    let pool_acc = next_account_info(accounts_iter)?;
+    let treasury_acc = next_account_info(accounts_iter)?;
+    let vault_acc = next_account_info(accounts_iter)?;
+    let mint_acc = next_account_info(accounts_iter)?;
+    let dst_token_acc = next_account_info(accounts_iter)?; // receives the treasury and vault balances
+    let dst_acc = next_account_info(accounts_iter)?; // receives the treasury and vault rent
+
+    let treasurer = next_account_info(accounts_iter)?;
+    let splt_program = next_account_info(accounts_iter)?;
+    let splt_2022_program = next_account_info(accounts_iter)?;
+    let splt_programs = &[splt_program, splt_2022_program];
+
+    Self::is_token_programs(splt_program, splt_2022_program)?;
+    Self::is_program(program_id, &[pool_acc])?;
+    Self::is_pool_owner(owner, pool_acc, program_id, accounts_iter.as_slice())?;
+
+    let mut pool_data = Pool::unpack(&pool_acc.data.borrow())?;
+    let seed: &[&[&[u8]]] = &[&[&Self::safe_seed(pool_acc, treasurer, program_id)?[..]]];
+    let (code, _) = pool_data
+      .get_reserve(treasury_acc.key)
+      .ok_or(AppError::UnmatchedPool)?;
+    // Token 0 prices the LPT, it can't be retired
+    if code == 0 || pool_data.vaults[code as usize] != *vault_acc.key {
+      return Err(AppError::UnmatchedPool.into());
+    }
+    if pool_data.mints[code as usize] != *mint_acc.key {
+      return Err(AppError::InvalidMint.into());
+    }
+    // The providers take the leg out through ExitPoolToken first, only donations are left
+    if pool_data.reserves[code as usize] != 0 {
+      return Err(AppError::NonEmptyReserve.into());
+    }
+
+    // Drain and close treasury_acc and vault_acc
+    Self::sweep_account(treasury_acc, mint_acc, dst_token_acc, dst_acc, treasurer, splt_programs, seed)?;
+    Self::sweep_account(vault_acc, mint_acc, dst_token_acc, dst_acc, treasurer, splt_programs, seed)?;
+
+    // Update pool data, the following tokens shift down by one slot
+    let index = code as usize;
+    pool_data.mints.remove(index);
+    pool_data.treasurys.remove(index);
+    pool_data.reserves.remove(index);
+    pool_data.mints.push(Pubkey::default());
+    pool_data.treasurys.push(Pubkey::default());
+    pool_data.reserves.push(0);
+    pool_data.vaults.remove(index);
+    pool_data.vaults.push(Pubkey::default());
+    pool_data.rate_providers.remove(index);
+    pool_data.rates.remove(index);
+    pool_data.rate_expiries.remove(index);
+    pool_data.rate_durations.remove(index);
+    pool_data.rate_providers.push(Pubkey::default());
+    pool_data.rates.push(0);
+    pool_data.rate_expiries.push(0);
+    pool_data.rate_durations.push(0);
+    pool_data.last_reserves.remove(index);
+    pool_data.last_reserves.push(0);
+    pool_data.reference_slot = 0; // the reference reserves don't follow the shift
+    pool_data.retiring_index = match pool_data.retiring() {
+      Some(retiring) if retiring > index => retiring as u8 - 1,
+      Some(retiring) if retiring < index => retiring as u8,
+      _ => 0,
+    };
+    Pool::pack(pool_data, &mut pool_acc.data.borrow_mut())?;
+
+    Ok(())
+  }
+
+  pub fn retire_pool_token(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    let owner = next_account_info(accounts_iter)?;
+    let pool_acc = next_account_info(accounts_iter)?;
+    let mint_lpt_acc = next_account_info(accounts_iter)?;
+    let treasury_acc = next_account_info(accounts_iter)?;
+
+    Self::is_program(program_id, &[pool_acc])?;
+    Self::is_pool_owner(owner, pool_acc, program_id, accounts_iter.as_slice())?;
+
+    let mint_lpt_data = Mint::unpack(&mint_lpt_acc.data.borrow())?;
+    let mut pool_data = Pool::unpack(&pool_acc.data.borrow())?;
+    if pool_data.mint_lpt != *mint_lpt_acc.key {
+      return Err(AppError::UnmatchedPool.into());
+    }
+    let (code, reserve) = pool_data
+      .get_reserve(treasury_acc.key)
+      .ok_or(AppError::UnmatchedPool)?;
+    // Token 0 prices the LPT, it can't be retired
+    if code == 0 {
+      return Err(AppError::UnmatchedPool.into());
+    }
+    if !pool_data.is_active() {
+      return Err(AppError::InactivePool.into());
+    }
+    if pool_data.retiring().is_some() {
+      return Err(AppError::RetiringToken.into());
+    }
+    if reserve == 0 {
+      return Err(AppError::ZeroValue.into());
+    }
+
+    // Every leg is worth the same share of the supply, which the exits pay for the leg.
+    // Deposits, imbalanced withdrawals and swaps of the leg are closed meanwhile, and
+    // proportional withdrawals keep the LPT per token, so the price holds until the leg is out
+    pool_data.retiring_index = code;
+    pool_data.retiring_lpt = mint_lpt_data
+      .supply
+      .checked_div(pool_data.token_count() as u64)
+      .ok_or(AppError::Overflow)?;
+    pool_data.retiring_reserve = reserve;
+    Pool::pack(pool_data, &mut pool_acc.data.borrow_mut())?;
+
+    Ok(())
+  }
+
+  pub fn exit_pool_token(
+    amount: u64,
+    max_lpt: u64,
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+  ) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    let owner = next_account_info(accounts_iter)?;
+    let pool_acc = next_account_info(accounts_iter)?;
+    let lpt_acc = next_account_info(accounts_iter)?;
+    let mint_lpt_acc = next_account_info(accounts_iter)?;
+    let dst_acc = next_account_info(accounts_iter)?;
+    let mint_acc = next_account_info(accounts_iter)?;
+    let treasury_acc = next_account_info(accounts_iter)?;
+
+    let treasurer = next_account_info(accounts_iter)?;
+    let splt_program = next_account_info(accounts_iter)?;
+    let splt_2022_program = next_account_info(accounts_iter)?;
+    let splt_programs = &[splt_program, splt_2022_program];
+    let system_program = next_account_info(accounts_iter)?;
+    let splata_program = next_account_info(accounts_iter)?;
+
+    Self::is_token_programs(splt_program, splt_2022_program)?;
+    Self::is_program(program_id, &[pool_acc])?;
+    Self::is_signer(&[owner])?;
+    let seed: &[&[&[u8]]] = &[&[&Self::safe_seed(pool_acc, treasurer, program_id)?[..]]];
+
+    let mut pool_data = Pool::unpack(&pool_acc.data.borrow())?;
+    if pool_data.mint_lpt != *mint_lpt_acc.key {
+      return Err(AppError::UnmatchedPool.into());
+    }
+    let (code, reserve) = pool_data
+      .get_reserve(treasury_acc.key)
+      .ok_or(AppError::UnmatchedPool)?;
+    if pool_data.retiring() != Some(code as usize) {
+      return Err(AppError::UnmatchedPool.into());
+    }
+    if pool_data.mints[code as usize] != *mint_acc.key {
+      return Err(AppError::InvalidMint.into());
+    }
+
+    if !pool_data.can_withdraw() {
+      return Err(AppError::PausedOperation.into());
+    }
+    if amount == 0 {
+      return Err(AppError::ZeroValue.into());
+    }
+    if amount > reserve {
+      return Err(AppError::ExceedReserve.into());
+    }
+
+    // The leg is paid for at the LPT per token of its retirement, rounded up
+    let lpt = (amount as u128)
+      .checked_mul(pool_data.retiring_lpt as u128)
+      .ok_or(AppError::Overflow)?
+      .checked_add(pool_data.retiring_reserve as u128 - 1)
+      .ok_or(AppError::Overflow)?
+      .checked_div(pool_data.retiring_reserve as u128)
+      .ok_or(AppError::Overflow)? as u64;
+    if lpt == 0 {
+      return Err(AppError::ZeroValue.into());
+    }
+    if lpt > max_lpt {
+      return Err(AppError::ExceedLimit.into());
+    }
+
+    XSPLT::burn(
+      lpt,
+      lpt_acc,
+      mint_lpt_acc,
+      owner,
+      Self::token_program(mint_lpt_acc, splt_programs)?,
+      seed,
+    )?;
+    pool_data.reserves[code as usize] = reserve - amount;
+    Pool::pack(pool_data, &mut pool_acc.data.borrow_mut())?;
+    // Withdraw token
+    Self::ensure_account(dst_acc, owner, owner, mint_acc, system_program, splt_programs, splata_program)?;
+    Self::withdraw(amount, treasury_acc, mint_acc, dst_acc, treasurer, splt_programs, seed)?;
+
+    Ok(())
+  }
+
+  pub fn close_pool(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    let payer = next_account_info(accounts_iter)?; // funds the token accounts of the locker
+    let owner = next_account_info(accounts_iter)?;
+    let pool_acc = next_account_info(accounts_iter)?;
+    let mint_lpt_acc = next_account_info(accounts_iter)?;
+    let dst_acc = next_account_info(accounts_iter)?; // receives all the lamports
+    let locker = next_account_info(accounts_iter)?; // owner of the locked LPT
+
+    let treasurer = next_account_info(accounts_iter)?;
+    let splt_program = next_account_info(accounts_iter)?;
+    let splt_2022_program = next_account_info(accounts_iter)?;
+    let splt_programs = &[splt_program, splt_2022_program];
+    let system_program = next_account_info(accounts_iter)?;
+    let splata_program = next_account_info(accounts_iter)?;
+
+    Self::is_token_programs(splt_program, splt_2022_program)?;
+    Self::is_program(program_id, &[pool_acc])?;
+    Self::is_signer(&[payer])?;
+
+    let mint_lpt_data = Mint::unpack(&mint_lpt_acc.data.borrow())?;
+    let pool_data = Pool::unpack(&pool_acc.data.borrow())?;
+    let seed: &[&[&[u8]]] = &[&[&Self::safe_seed(pool_acc, treasurer, program_id)?[..]]];
+    if pool_data.mint_lpt != *mint_lpt_acc.key {
+      return Err(AppError::UnmatchedPool.into());
+    }
+    let (locker_key, _) = Pubkey::find_program_address(&[&pool_acc.key.to_bytes(), LOCKER_SEED], program_id);
+    if *locker.key != locker_key {
+      return Err(AppError::InvalidOwner.into());
+    }
+
+    // treasury_acc, vault_acc, mint_acc, lock_token_acc, dst_token_acc for every token of the pool
+    let token_count = pool_data.token_count();
+    let mut treasury_accs = Vec::with_capacity(token_count);
+    let mut vault_accs = Vec::with_capacity(token_count);
+    let mut mint_accs = Vec::with_capacity(token_count);
+    let mut lock_token_accs = Vec::with_capacity(token_count);
+    let mut dst_token_accs = Vec::with_capacity(token_count);
+    for i in 0..token_count {
+      let treasury_acc = next_account_info(accounts_iter)?;
+      let vault_acc = next_account_info(accounts_iter)?;
+      let mint_acc = next_account_info(accounts_iter)?;
+      let lock_token_acc = next_account_info(accounts_iter)?; // associated account of the locker
+      let dst_token_acc = next_account_info(accounts_iter)?; // receives the vault earnings
+      if pool_data.treasurys[i] != *treasury_acc.key || pool_data.vaults[i] != *vault_acc.key {
+        return Err(AppError::UnmatchedPool.into());
+      }
+      if pool_data.mints[i] != *mint_acc.key {
+        return Err(AppError::InvalidMint.into());
+      }
+      if *lock_token_acc.key != Self::associated_address(locker, mint_acc) {
+        return Err(AppError::InvalidOwner.into());
+      }
+      treasury_accs.push(treasury_acc);
+      vault_accs.push(vault_acc);
+      mint_accs.push(mint_acc);
+      lock_token_accs.push(lock_token_acc);
+      dst_token_accs.push(dst_token_acc);
+    }
+
+    Self::is_pool_owner(owner, pool_acc, program_id, accounts_iter.as_slice())?;
+
+    // Only the locked liquidity may be left. The treasuries back the locked LPT, so they are
+    // swept to the locker, and the vaults hold the earnings of the pool owner
+    if mint_lpt_data.supply > MINIMUM_LIQUIDITY {
+      return Err(AppError::NonEmptyReserve.into());
+    }
+    for i in 0..token_count {
+      Self::ensure_account(
+        lock_token_accs[i],
+        locker,
+        payer,
+        mint_accs[i],
+        system_program,
+        splt_programs,
+        splata_program,
+      )?;
+      Self::sweep_account(
+        treasury_accs[i],
+        mint_accs[i],
+        lock_token_accs[i],
+        dst_acc,
+        treasurer,
+        splt_programs,
+        seed,
+      )?;
+      Self::sweep_account(
+        vault_accs[i],
+        mint_accs[i],
+        dst_token_accs[i],
+        dst_acc,
+        treasurer,
+        splt_programs,
+        seed,
+      )?;
+    }
+
+    // Close pool_acc
+    let lamports = dst_acc
+      .lamports()
+      .checked_add(pool_acc.lamports())
+      .ok_or(AppError::Overflow)?;
+    **dst_acc.lamports.borrow_mut() = lamports;
+    **pool_acc.lamports.borrow_mut() = 0;
+    pool_acc.data.borrow_mut().fill(0);
+
+    Ok(())
+  }
+
+  pub fn add_liquidity_balanced(
+    lpt_out: u64,
+    max_amounts: Vec<u64>,
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+  ) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    let owner = next_account_info(accounts_iter)?;
+    let pool_acc = next_account_info(accounts_iter)?;
+    let lpt_acc = next_account_info(accounts_iter)?;
+    let mint_lpt_acc = next_account_info(accounts_iter)?;
+
+    let treasurer = next_account_info(accounts_iter)?;
+    let splt_program = next_account_info(accounts_iter)?;
+    let splt_2022_program = next_account_info(accounts_iter)?;
+    let splt_programs = &[splt_program, splt_2022_program];
+
+    Self::is_token_programs(splt_program, splt_2022_program)?;
+    Self::is_program(program_id, &[pool_acc])?;
+    Self::is_signer(&[owner])?;
+
+    let mint_lpt_data = Mint::unpack(&mint_lpt_acc.data.borrow())?;
+    let mut pool_data = Pool::unpack(&pool_acc.data.borrow())?;
+    let seed: &[&[&[u8]]] = &[&[&Self::safe_seed(pool_acc, treasurer, program_id)?[..]]];
+
+    if pool_data.mint_lpt != *mint_lpt_acc.key {
+      return Err(AppError::InvalidOwner.into());
+    }
+
+    // src_acc, mint_acc, treasury_acc for every token of the pool
+    let token_count = pool_data.token_count();
+    if max_amounts.len() != token_count {
+      return Err(AppError::InvalidInstruction.into());
+    }
+    let mut src_accs = Vec::with_capacity(token_count);
+    let mut mint_accs = Vec::with_capacity(token_count);
+    let mut treasury_accs = Vec::with_capacity(token_count);
+    for i in 0..token_count {
+      let src_acc = next_account_info(accounts_iter)?;
+      let mint_acc = next_account_info(accounts_iter)?;
+      let treasury_acc = next_account_info(accounts_iter)?;
+      if pool_data.treasurys[i] != *treasury_acc.key {
+        return Err(AppError::InvalidOwner.into());
+      }
+      if pool_data.mints[i] != *mint_acc.key {
+        return Err(AppError::InvalidMint.into());
+      }
+      src_accs.push(src_acc);
+      mint_accs.push(mint_acc);
+      treasury_accs.push(treasury_acc);
+    }
+
+    if !pool_data.is_active() {
+      return Err(AppError::InactivePool.into());
+    }
+    if pool_data.is_paused(PAUSE_DEPOSIT) {
+      return Err(AppError::PausedOperation.into());
+    }
+    // Deposits would move the LPT per token of the retiring leg
+    if pool_data.retiring().is_some() {
+      return Err(AppError::RetiringToken.into());
+    }
+
+    if lpt_out == 0 {
+      return Err(AppError::ZeroValue.into());
+    }
+
+    // Pro-rata deposit, rounded up in favor of the pool. Scaling a leg by its rate scales its
+    // delta alike, so the rates cancel out, but like any deposit it waits for them to be fresh
+    let now = Clock::get()?.unix_timestamp;
+    let scaled_reserves =
+      Self::scale_amounts(&pool_data, &pool_data.reserves[0..token_count], now, Oracle::scale)?;
+    let mut deltas = Vec::with_capacity(token_count);
+    for i in 0..token_count {
+      let rate = pool_data.get_rate(i, now).ok_or(AppError::ExpiredRate)?;
+      let scaled_delta = (lpt_out as u128)
+        .checked_mul(scaled_reserves[i] as u128)
+        .ok_or(AppError::Overflow)?
+        .checked_add(mint_lpt_data.supply as u128)
+        .ok_or(AppError::Overflow)?
+        .checked_sub(1)
+        .ok_or(AppError::Overflow)?
+        .checked_div(mint_lpt_data.supply as u128)
+        .ok_or(AppError::Overflow)? as u64;
+      let delta = Oracle::unscale_up(scaled_delta, rate).ok_or(AppError::Overflow)?;
+      // Gross up for the transfer fee so the treasury receives the whole delta
+      let amount = Self::pre_fee_amount(delta, mint_accs[i], splt_programs)?;
+      if amount > max_amounts[i] {
+        return Err(AppError::ExceedLimit.into());
+      }
+      pool_data.reserves[i] = pool_data.reserves[i]
+        .checked_add(delta)
+        .ok_or(AppError::Overflow)?;
+      deltas.push((delta, amount));
+    }
+
+    // Deposit token
+    for i in 0..token_count {
+      let (delta, amount) = deltas[i];
+      let received_delta = Self::deposit(
+        amount,
+        src_accs[i],
+        mint_accs[i],
+        treasury_accs[i],
+        owner,
+        splt_programs,
+      )?;
+      if received_delta < delta {
+        return Err(AppError::InsufficientFunds.into());
+      }
+    }
+
+    // Update pool
+    Pool::pack(pool_data, &mut pool_acc.data.borrow_mut())?;
+    // Mint LPT
+    XSPLT::mint_to(lpt_out, mint_lpt_acc, lpt_acc, treasurer, splt_program, seed)?;
+
+    Ok(())
+  }
+
+  pub fn remove_liquidity_imbalanced(
+    amounts: Vec<u64>,
+    max_lpt: u64,
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+  ) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    let owner = next_account_info(accounts_iter)?;
+    let pool_acc = next_account_info(accounts_iter)?;
+    let lpt_acc = next_account_info(accounts_iter)?;
+    let mint_lpt_acc = next_account_info(accounts_iter)?;
+
+    let treasurer = next_account_info(accounts_iter)?;
+    let splt_program = next_account_info(accounts_iter)?;
+    let splt_2022_program = next_account_info(accounts_iter)?;
+    let splt_programs = &[splt_program, splt_2022_program];
+
+    Self::is_token_programs(splt_program, splt_2022_program)?;
+    Self::is_program(program_id, &[pool_acc])?;
+    Self::is_signer(&[owner])?;
+    let seed: &[&[&[u8]]] = &[&[&Self::safe_seed(pool_acc, treasurer, program_id)?[..]]];
+
+    let mint_lpt_data = Mint::unpack(&mint_lpt_acc.data.borrow())?;
+    let mut pool_data = Pool::unpack(&pool_acc.data.borrow())?;
+    if pool_data.mint_lpt != *mint_lpt_acc.key {
+      return Err(AppError::UnmatchedPool.into());
+    }
+
+    // dst_acc, mint_acc, treasury_acc for every token of the pool
+    let token_count = pool_data.token_count();
+    if amounts.len() != token_count {
+      return Err(AppError::InvalidInstruction.into());
+    }
+    let mut dst_accs = Vec::with_capacity(token_count);
+    let mut mint_accs = Vec::with_capacity(token_count);
+    let mut treasury_accs = Vec::with_capacity(token_count);
+    for i in 0..token_count {
+      let dst_acc = next_account_info(accounts_iter)?;
+      let mint_acc = next_account_info(accounts_iter)?;
+      let treasury_acc = next_account_info(accounts_iter)?;
+      if pool_data.treasurys[i] != *treasury_acc.key {
+        return Err(AppError::UnmatchedPool.into());
+      }
+      if pool_data.mints[i] != *mint_acc.key {
+        return Err(AppError::InvalidMint.into());
+      }
+      dst_accs.push(dst_acc);
+      mint_accs.push(mint_acc);
+      treasury_accs.push(treasury_acc);
+    }
+
+    // Only proportional exits are open to a pool winding down
+    if !pool_data.is_active() {
+      return Err(AppError::InactivePool.into());
+    }
+    if pool_data.is_paused(PAUSE_WITHDRAW) {
+      return Err(AppError::PausedOperation.into());
+    }
+    // The retiring leg leaves through ExitPoolToken, proportional withdrawals stay open
+    if pool_data.retiring().is_some() {
+      return Err(AppError::RetiringToken.into());
+    }
+
+    // Yield-bearing tokens are valued in the underlying through their cached rates
+    let now = Clock::get()?.unix_timestamp;
+    let (lpt, _) = Oracle::unrake(
+      // Withdrawals round up in favor of the pool
+      Self::scale_amounts(&pool_data, &amounts, now, Oracle::scale_up)?,
+      Self::scale_amounts(&pool_data, &pool_data.reserves[0..token_count], now, Oracle::scale)?,
+      mint_lpt_data.supply,
+    )
+    .ok_or(AppError::Overflow)?;
+    if lpt == 0 {
+      return Err(AppError::ZeroValue.into());
+    }
+    if lpt > max_lpt {
+      return Err(AppError::ExceedLimit.into());
+    }
+
+    XSPLT::burn(
+      lpt,
+      lpt_acc,
+      mint_lpt_acc,
+      owner,
+      Self::token_program(mint_lpt_acc, splt_programs)?,
+      seed,
+    )?;
+
+    // Update pool
+    for (reserve, amount) in pool_data.reserves.iter_mut().zip(&amounts) {
+      *reserve = reserve
+        .checked_sub(*amount)
+        .ok_or(AppError::Overflow)?;
+    }
+    Pool::pack(pool_data, &mut pool_acc.data.borrow_mut())?;
+    // Withdraw token
+    for i in 0..token_count {
+      if amounts[i] > 0 {
+        Self::withdraw(
+          amounts[i],
+          treasury_accs[i],
+          mint_accs[i],
+          dst_accs[i],
+          treasurer,
+          splt_programs,
+          seed,
+        )?;
+      }
+    }
+
+    Ok(())
+  }
+
+  pub fn initialize_allowlist(
+    earning: u64,
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+  ) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    let owner = next_account_info(accounts_iter)?;
+    let pool_acc = next_account_info(accounts_iter)?;
+    let allowlist_acc = next_account_info(accounts_iter)?;
+
+    Self::is_program(program_id, &[pool_acc, allowlist_acc])?;
+    Self::is_signer(&[allowlist_acc])?;
+    Self::is_pool_owner(owner, pool_acc, program_id, accounts_iter.as_slice())?;
+
+    let mut allowlist_data = Allowlist::unpack_unchecked(&allowlist_acc.data.borrow())?;
+    if allowlist_data.is_initialized() {
+      return Err(AppError::ConstructorOnce.into());
+    }
+    if earning > EARNING {
+      return Err(AppError::ExceedLimit.into());
+    }
+
+    // Update allowlist data
+    allowlist_data.pool = *pool_acc.key;
+    allowlist_data.earning = earning;
+    allowlist_data.is_initialized = true;
+    Allowlist::pack(allowlist_data, &mut allowlist_acc.data.borrow_mut())?;
+
+    Ok(())
+  }
+
+  pub fn add_allowlist_trader(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    let owner = next_account_info(accounts_iter)?;
+    let pool_acc = next_account_info(accounts_iter)?;
+    let allowlist_acc = next_account_info(accounts_iter)?;
+    let trader = next_account_info(accounts_iter)?;
+
+    Self::is_program(program_id, &[pool_acc, allowlist_acc])?;
+    Self::is_pool_owner(owner, pool_acc, program_id, accounts_iter.as_slice())?;
+
+    let mut allowlist_data = Allowlist::unpack(&allowlist_acc.data.borrow())?;
+    if allowlist_data.pool != *pool_acc.key {
+      return Err(AppError::UnmatchedPool.into());
+    }
+    if allowlist_data.contains(trader.key) {
+      return Err(AppError::DuplicatedTrader.into());
+    }
+    if !allowlist_data.add(trader.key) {
+      return Err(AppError::FullAllowlist.into());
+    }
+    Allowlist::pack(allowlist_data, &mut allowlist_acc.data.borrow_mut())?;
+
+    Ok(())
+  }
+
+  pub fn remove_allowlist_trader(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    let owner = next_account_info(accounts_iter)?;
+    let pool_acc = next_account_info(accounts_iter)?;
+    let allowlist_acc = next_account_info(accounts_iter)?;
+    let trader = next_account_info(accounts_iter)?;
+
+    Self::is_program(program_id, &[pool_acc, allowlist_acc])?;
+    Self::is_pool_owner(owner, pool_acc, program_id, accounts_iter.as_slice())?;
+
+    let mut allowlist_data = Allowlist::unpack(&allowlist_acc.data.borrow())?;
+    if allowlist_data.pool != *pool_acc.key {
+      return Err(AppError::UnmatchedPool.into());
+    }
+    if !allowlist_data.remove(trader.key) {
+      return Err(AppError::UnknownTrader.into());
+    }
+    Allowlist::pack(allowlist_data, &mut allowlist_acc.data.borrow_mut())?;
+
+    Ok(())
+  }
+
+  pub fn set_allowlist_earning(
+    earning: u64,
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+  ) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    let owner = next_account_info(accounts_iter)?;
+    let pool_acc = next_account_info(accounts_iter)?;
+    let allowlist_acc = next_account_info(accounts_iter)?;
+
+    Self::is_program(program_id, &[pool_acc, allowlist_acc])?;
+    Self::is_pool_owner(owner, pool_acc, program_id, accounts_iter.as_slice())?;
+
+    let mut allowlist_data = Allowlist::unpack(&allowlist_acc.data.borrow())?;
+    if allowlist_data.pool != *pool_acc.key {
+      return Err(AppError::UnmatchedPool.into());
+    }
+    if earning > EARNING {
+      return Err(AppError::ExceedLimit.into());
+    }
+    allowlist_data.earning = earning;
+    Allowlist::pack(allowlist_data, &mut allowlist_acc.data.borrow_mut())?;
+
+    Ok(())
+  }
+
+  pub fn set_referral_share(share: u64, program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    let owner = next_account_info(accounts_iter)?;
+    let pool_acc = next_account_info(accounts_iter)?;
+
+    Self::is_program(program_id, &[pool_acc])?;
+    Self::is_pool_owner(owner, pool_acc, program_id, accounts_iter.as_slice())?;
+
+    if share > MAX_REFERRAL_SHARE {
+      return Err(AppError::ExceedLimit.into());
+    }
+
+    // Update pool data
+    let mut pool_data = Pool::unpack(&pool_acc.data.borrow())?;
+    pool_data.referral_share = share;
+    Pool::pack(pool_data, &mut pool_acc.data.borrow_mut())?;
+
+    Ok(())
+  }
+
+  pub fn set_fee_tiers(
+    fee_tiers: Vec<FeeTier>,
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+  ) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    let owner = next_account_info(accounts_iter)?;
+    let pool_acc = next_account_info(accounts_iter)?;
+
+    Self::is_program(program_id, &[pool_acc])?;
+    Self::is_pool_owner(owner, pool_acc, program_id, accounts_iter.as_slice())?;
+
+    // Volumes go strictly up from tier to tier
+    if fee_tiers.len() > FEE_TIER_COUNT {
+      return Err(AppError::InvalidFeeTiers.into());
+    }
+    for (i, fee_tier) in fee_tiers.iter().enumerate() {
+      if fee_tier.discount > MAX_FEE_DISCOUNT {
+        return Err(AppError::InvalidFeeTiers.into());
+      }
+      if i > 0 && fee_tier.volume <= fee_tiers[i - 1].volume {
+        return Err(AppError::InvalidFeeTiers.into());
+      }
+    }
+
+    // Update pool data
+    let mut pool_data = Pool::unpack(&pool_acc.data.borrow())?;
+    pool_data.fee_tiers = fee_tiers;
+    Pool::pack(pool_data, &mut pool_acc.data.borrow_mut())?;
+
+    Ok(())
+  }
+
+  pub fn initialize_trader_volume(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    let trader = next_account_info(accounts_iter)?;
+    let pool_acc = next_account_info(accounts_iter)?;
+    let volume_acc = next_account_info(accounts_iter)?;
+
+    Self::is_program(program_id, &[pool_acc, volume_acc])?;
+    Self::is_signer(&[trader, volume_acc])?;
+
+    let mut volume_data = TraderVolume::unpack_unchecked(&volume_acc.data.borrow())?;
+    if volume_data.is_initialized() {
+      return Err(AppError::ConstructorOnce.into());
+    }
+
+    // Update volume data
+    volume_data.pool = *pool_acc.key;
+    volume_data.trader = *trader.key;
+    volume_data.is_initialized = true;
+    volume_data.last_day = (Clock::get()?.unix_timestamp / SECONDS_PER_DAY) as u64;
+    TraderVolume::pack(volume_data, &mut volume_acc.data.borrow_mut())?;
+
+    Ok(())
+  }
+
+  pub fn set_holder_tiers(
+    holder_tiers: Vec<HolderTier>,
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+  ) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    let owner = next_account_info(accounts_iter)?;
+    let pool_acc = next_account_info(accounts_iter)?;
+    let discount_mint_acc = next_account_info(accounts_iter)?;
+
+    Self::is_program(program_id, &[pool_acc])?;
+    Self::is_pool_owner(owner, pool_acc, program_id, accounts_iter.as_slice())?;
+
+    // Balances go strictly up from tier to tier
+    if holder_tiers.len() > HOLDER_TIER_COUNT {
+      return Err(AppError::InvalidFeeTiers.into());
+    }
+    for (i, holder_tier) in holder_tiers.iter().enumerate() {
+      if holder_tier.discount > MAX_FEE_DISCOUNT {
+        return Err(AppError::InvalidFeeTiers.into());
+      }
+      if i > 0 && holder_tier.balance <= holder_tiers[i - 1].balance {
+        return Err(AppError::InvalidFeeTiers.into());
+      }
+    }
+    Mint::unpack_with_extensions(&discount_mint_acc.data.borrow())?;
+
+    // Update pool data
+    let mut pool_data = Pool::unpack(&pool_acc.data.borrow())?;
+    pool_data.discount_mint = *discount_mint_acc.key;
+    pool_data.holder_tiers = holder_tiers;
+    Pool::pack(pool_data, &mut pool_acc.data.borrow_mut())?;
+
+    Ok(())
+  }
+
+  pub fn set_dynamic_fee(
+    is_enabled: bool,
+    min_fee: u64,
+    max_fee: u64,
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+  ) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    let owner = next_account_info(accounts_iter)?;
+    let pool_acc = next_account_info(accounts_iter)?;
+
+    Self::is_program(program_id, &[pool_acc])?;
+    Self::is_pool_owner(owner, pool_acc, program_id, accounts_iter.as_slice())?;
+
+    if min_fee > max_fee || max_fee > MAX_DYNAMIC_FEE {
+      return Err(AppError::InvalidFeeBounds.into());
+    }
+
+    // Update pool data, the volatility starts over
+    let mut pool_data = Pool::unpack(&pool_acc.data.borrow())?;
+    pool_data.is_dynamic_fee = is_enabled;
+    pool_data.min_fee = min_fee;
+    pool_data.max_fee = max_fee;
+    pool_data.volatility = 0;
+    pool_data.last_slot = Clock::get()?.slot;
+    pool_data.last_reserves = pool_data.reserves.clone();
+    Pool::pack(pool_data, &mut pool_acc.data.borrow_mut())?;
+
+    Ok(())
+  }
+
+  pub fn set_circuit_breaker(
+    max_deviation: u16,
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+  ) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    let owner = next_account_info(accounts_iter)?;
+    let pool_acc = next_account_info(accounts_iter)?;
+
+    Self::is_program(program_id, &[pool_acc])?;
+    Self::is_pool_owner(owner, pool_acc, program_id, accounts_iter.as_slice())?;
+
+    if max_deviation > MAX_DEVIATION_BPS {
+      return Err(AppError::ExceedLimit.into());
+    }
+
+    // Update pool data
+    let mut pool_data = Pool::unpack(&pool_acc.data.borrow())?;
+    pool_data.max_deviation = max_deviation;
+    Pool::pack(pool_data, &mut pool_acc.data.borrow_mut())?;
+
+    Ok(())
+  }
+
+  pub fn set_trade_limits(
+    max_bid_ratio: u16,
+    max_ask_ratio: u16,
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+  ) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    let owner = next_account_info(accounts_iter)?;
+    let pool_acc = next_account_info(accounts_iter)?;
+
+    Self::is_program(program_id, &[pool_acc])?;
+    Self::is_pool_owner(owner, pool_acc, program_id, accounts_iter.as_slice())?;
+
+    if max_bid_ratio > MAX_TRADE_RATIO_BPS || max_ask_ratio > MAX_TRADE_RATIO_BPS {
+      return Err(AppError::ExceedLimit.into());
+    }
+
+    // Update pool data
+    let mut pool_data = Pool::unpack(&pool_acc.data.borrow())?;
+    pool_data.max_bid_ratio = max_bid_ratio;
+    pool_data.max_ask_ratio = max_ask_ratio;
+    Pool::pack(pool_data, &mut pool_acc.data.borrow_mut())?;
+
+    Ok(())
+  }
+
+  pub fn set_anti_sandwich(is_enabled: bool, program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    let owner = next_account_info(accounts_iter)?;
+    let pool_acc = next_account_info(accounts_iter)?;
+
+    Self::is_program(program_id, &[pool_acc])?;
+    Self::is_pool_owner(owner, pool_acc, program_id, accounts_iter.as_slice())?;
+
+    // Update pool data
+    let mut pool_data = Pool::unpack(&pool_acc.data.borrow())?;
+    pool_data.is_anti_sandwich = is_enabled;
+    Pool::pack(pool_data, &mut pool_acc.data.borrow_mut())?;
+
+    Ok(())
+  }
+
+  pub fn initialize_router_registry(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    let owner = next_account_info(accounts_iter)?;
+    let pool_acc = next_account_info(accounts_iter)?;
+    let registry_acc = next_account_info(accounts_iter)?;
+
+    Self::is_program(program_id, &[pool_acc, registry_acc])?;
+    Self::is_signer(&[registry_acc])?;
+    Self::is_pool_owner(owner, pool_acc, program_id, accounts_iter.as_slice())?;
+
+    let mut registry_data = RouterRegistry::unpack_unchecked(&registry_acc.data.borrow())?;
+    if registry_data.is_initialized() {
+      return Err(AppError::ConstructorOnce.into());
+    }
+
+    // Update registry data
+    registry_data.pool = *pool_acc.key;
+    registry_data.is_initialized = true;
+    RouterRegistry::pack(registry_data, &mut registry_acc.data.borrow_mut())?;
+
+    Ok(())
+  }
+
+  pub fn add_router(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    let owner = next_account_info(accounts_iter)?;
+    let pool_acc = next_account_info(accounts_iter)?;
+    let registry_acc = next_account_info(accounts_iter)?;
+    let router_program = next_account_info(accounts_iter)?;
+
+    Self::is_program(program_id, &[pool_acc, registry_acc])?;
+    Self::is_pool_owner(owner, pool_acc, program_id, accounts_iter.as_slice())?;
+
+    let mut registry_data = RouterRegistry::unpack(&registry_acc.data.borrow())?;
+    if registry_data.pool != *pool_acc.key {
+      return Err(AppError::UnmatchedPool.into());
+    }
+    if !router_program.executable {
+      return Err(AppError::IncorrectProgramId.into());
+    }
+    if registry_data.contains(router_program.key) {
+      return Err(AppError::DuplicatedRouter.into());
+    }
+    if !registry_data.add(router_program.key) {
+      return Err(AppError::FullRegistry.into());
+    }
+    RouterRegistry::pack(registry_data, &mut registry_acc.data.borrow_mut())?;
+
+    Ok(())
+  }
+
+  pub fn remove_router(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    let owner = next_account_info(accounts_iter)?;
+    let pool_acc = next_account_info(accounts_iter)?;
+    let registry_acc = next_account_info(accounts_iter)?;
+    let router_program = next_account_info(accounts_iter)?;
+
+    Self::is_program(program_id, &[pool_acc, registry_acc])?;
+    Self::is_pool_owner(owner, pool_acc, program_id, accounts_iter.as_slice())?;
+
+    let mut registry_data = RouterRegistry::unpack(&registry_acc.data.borrow())?;
+    if registry_data.pool != *pool_acc.key {
+      return Err(AppError::UnmatchedPool.into());
+    }
+    if !registry_data.remove(router_program.key) {
+      return Err(AppError::UnknownRouter.into());
+    }
+    RouterRegistry::pack(registry_data, &mut registry_acc.data.borrow_mut())?;
+
+    Ok(())
+  }
+
+  pub fn set_price_peg(
+    max_staleness: u64,
+    spread: u16,
+    max_confidence: u16,
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+  ) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    let owner = next_account_info(accounts_iter)?;
+    let pool_acc = next_account_info(accounts_iter)?;
+    let price_acc = next_account_info(accounts_iter)?; // the program id unpegs the pool
+
+    Self::is_program(program_id, &[pool_acc])?;
+    Self::is_pool_owner(owner, pool_acc, program_id, accounts_iter.as_slice())?;
+
+    if spread > MAX_DEVIATION_BPS || max_confidence > MAX_DEVIATION_BPS {
+      return Err(AppError::ExceedLimit.into());
+    }
+
+    let mut pool_data = Pool::unpack(&pool_acc.data.borrow())?;
+    if *price_acc.key == *program_id {
+      pool_data.price_acc = Pubkey::default();
+    } else {
+      // Only the layout is checked, not the program owning the account: the pool owner is
+      // trusted to pick a genuine feed, since whoever writes to it sets the pegged price
+      Price::unpack(&price_acc.data.borrow())?;
+      pool_data.price_acc = *price_acc.key;
+    }
+    // Update pool data
+    pool_data.max_staleness = max_staleness;
+    pool_data.spread = spread;
+    pool_data.max_confidence = max_confidence;
+    Pool::pack(pool_data, &mut pool_acc.data.borrow_mut())?;
+
+    Ok(())
+  }
+
+  pub fn initialize_rate_provider(rate: u64, program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    let updater = next_account_info(accounts_iter)?;
+    let rate_acc = next_account_info(accounts_iter)?;
+
+    Self::is_program(program_id, &[rate_acc])?;
+    Self::is_signer(&[updater, rate_acc])?;
+
+    let mut rate_data = RateProvider::unpack_unchecked(&rate_acc.data.borrow())?;
+    if rate_data.is_initialized() {
+      return Err(AppError::ConstructorOnce.into());
+    }
+    if rate == 0 {
+      return Err(AppError::ZeroValue.into());
+    }
+
+    // Update rate data
+    rate_data.updater = *updater.key;
+    rate_data.is_initialized = true;
+    rate_data.rate = rate;
+    rate_data.updated_at = Clock::get()?.unix_timestamp;
+    RateProvider::pack(rate_data, &mut rate_acc.data.borrow_mut())?;
+
+    Ok(())
+  }
+
+  pub fn update_rate(rate: u64, program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    let updater = next_account_info(accounts_iter)?;
+    let rate_acc = next_account_info(accounts_iter)?;
+
+    Self::is_program(program_id, &[rate_acc])?;
+    Self::is_signer(&[updater])?;
+
+    let mut rate_data = RateProvider::unpack(&rate_acc.data.borrow())?;
+    if rate_data.updater != *updater.key {
+      return Err(AppError::InvalidOwner.into());
+    }
+    if rate == 0 {
+      return Err(AppError::ZeroValue.into());
+    }
+
+    // Update rate data
+    rate_data.rate = rate;
+    rate_data.updated_at = Clock::get()?.unix_timestamp;
+    RateProvider::pack(rate_data, &mut rate_acc.data.borrow_mut())?;
+
+    Ok(())
+  }
+
+  pub fn set_pool_rate_provider(
+    rate_duration: i64,
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+  ) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    let owner = next_account_info(accounts_iter)?;
+    let pool_acc = next_account_info(accounts_iter)?;
+    let treasury_acc = next_account_info(accounts_iter)?;
+    let rate_acc = next_account_info(accounts_iter)?; // the program id drops the provider
+
+    Self::is_program(program_id, &[pool_acc])?;
+    Self::is_pool_owner(owner, pool_acc, program_id, accounts_iter.as_slice())?;
+
+    if rate_duration <= 0 {
+      return Err(AppError::ZeroValue.into());
+    }
+
+    let mut pool_data = Pool::unpack(&pool_acc.data.borrow())?;
+    let (code, _) = pool_data
+      .get_reserve(treasury_acc.key)
+      .ok_or(AppError::UnmatchedPool)?;
+    let index = code as usize;
+    if *rate_acc.key == *program_id {
+      pool_data.rate_providers[index] = Pubkey::default();
+    } else {
+      Self::is_program(program_id, &[rate_acc])?;
+      RateProvider::unpack(&rate_acc.data.borrow())?;
+      pool_data.rate_providers[index] = *rate_acc.key;
+    }
+    // Update pool data, the token can't be traded until its rate is refreshed
+    pool_data.rates[index] = 0;
+    pool_data.rate_expiries[index] = 0;
+    pool_data.rate_durations[index] = rate_duration;
+    Pool::pack(pool_data, &mut pool_acc.data.borrow_mut())?;
+
+    Ok(())
+  }
+
+  pub fn refresh_rates(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    let pool_acc = next_account_info(accounts_iter)?;
+    let rate_accs = accounts_iter.as_slice(); // any of the pool rate providers
+
+    Self::is_program(program_id, &[pool_acc])?;
+
+    let mut pool_data = Pool::unpack(&pool_acc.data.borrow())?;
+    let now = Clock::get()?.unix_timestamp;
+    for rate_acc in rate_accs.iter() {
+      Self::is_program(program_id, &[rate_acc])?;
+      let rate_data = RateProvider::unpack(&rate_acc.data.borrow())?;
+      for i in 0..pool_data.token_count() {
+        if pool_data.rate_providers[i] == *rate_acc.key {
+          pool_data
+            .cache_rate(i, rate_data.rate, rate_data.updated_at, now)
+            .ok_or(AppError::ExpiredRate)?;
+        }
+      }
+    }
+    Pool::pack(pool_data, &mut pool_acc.data.borrow_mut())?;
+
+    Ok(())
+  }
+
+  pub fn set_curve(curve: Curve, program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    let owner = next_account_info(accounts_iter)?;
+    let pool_acc = next_account_info(accounts_iter)?;
+
+    Self::is_program(program_id, &[pool_acc])?;
+    Self::is_pool_owner(owner, pool_acc, program_id, accounts_iter.as_slice())?;
+
+    // Update pool data
+    let mut pool_data = Pool::unpack(&pool_acc.data.borrow())?;
+    pool_data.curve = curve;
+    // The breaker reference was taken under the previous curve
+    pool_data.reference_slot = 0;
+    Pool::pack(pool_data, &mut pool_acc.data.borrow_mut())?;
+
+    Ok(())
+  }
+
+  pub fn set_earning(is_enabled: bool, program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    let owner = next_account_info(accounts_iter)?;
+    let pool_acc = next_account_info(accounts_iter)?;
+
+    Self::is_program(program_id, &[pool_acc])?;
+    Self::is_pool_owner(owner, pool_acc, program_id, accounts_iter.as_slice())?;
+
+    // Update pool data
+    let mut pool_data = Pool::unpack(&pool_acc.data.borrow())?;
+    pool_data.is_earning = is_enabled;
+    Pool::pack(pool_data, &mut pool_acc.data.borrow_mut())?;
+
+    Ok(())
+  }
+
+  pub fn set_minimum_reserve(minimum: u64, program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    let authority = next_account_info(accounts_iter)?; // mint authority, pays for minimum_acc
+    let mint_acc = next_account_info(accounts_iter)?;
+    let minimum_acc = next_account_info(accounts_iter)?; // derived from [mint, MINIMUM_RESERVE_SEED]
+    let system_program = next_account_info(accounts_iter)?;
+    let sysvar_rent_acc = next_account_info(accounts_iter)?;
+
+    Self::is_signer(&[authority])?;
+    if *mint_acc.owner != spl_token::id() && *mint_acc.owner != spl_token_2022::id() {
+      return Err(AppError::IncorrectProgramId.into());
+    }
+    let (mint_data, _) = Mint::unpack_with_extensions(&mint_acc.data.borrow())?;
+    if mint_data.mint_authority != COption::Some(*authority.key) {
+      return Err(AppError::InvalidOwner.into());
+    }
+    if minimum == 0 {
+      return Err(AppError::ZeroValue.into());
+    }
+    let (minimum_key, bump) = Pubkey::find_program_address(
+      &[&mint_acc.key.to_bytes(), MINIMUM_RESERVE_SEED],
+      program_id,
+    );
+    if *minimum_acc.key != minimum_key {
+      return Err(AppError::InvalidOwner.into());
+    }
+
+    // The first call creates the account at its derived address
+    if minimum_acc.data_is_empty() {
+      let rent = Rent::from_account_info(sysvar_rent_acc)?;
+      let lamports = rent.minimum_balance(MinimumReserve::LEN);
+      let mint_seed = mint_acc.key.to_bytes();
+      let seed: &[&[&[u8]]] = &[&[&mint_seed, MINIMUM_RESERVE_SEED, &[bump]]];
+      if minimum_acc.lamports() == 0 {
+        invoke_signed(
+          &system_instruction::create_account(
+            authority.key,
+            minimum_acc.key,
+            lamports,
+            MinimumReserve::LEN as u64,
+            program_id,
+          ),
+          &[authority.clone(), minimum_acc.clone(), system_program.clone()],
+          seed,
+        )?;
+      } else {
+        // Anyone can fund the derived address ahead of time and make create_account fail,
+        // so the rent is topped up and the account allocated and assigned one by one
+        let top_up = lamports.saturating_sub(minimum_acc.lamports());
+        if top_up > 0 {
+          invoke(
+            &system_instruction::transfer(authority.key, minimum_acc.key, top_up),
+            &[authority.clone(), minimum_acc.clone(), system_program.clone()],
+          )?;
+        }
+        invoke_signed(
+          &system_instruction::allocate(minimum_acc.key, MinimumReserve::LEN as u64),
+          &[minimum_acc.clone(), system_program.clone()],
+          seed,
+        )?;
+        invoke_signed(
+          &system_instruction::assign(minimum_acc.key, program_id),
+          &[minimum_acc.clone(), system_program.clone()],
+          seed,
+        )?;
+      }
+    }
+    Self::is_program(program_id, &[minimum_acc])?;
+
+    // Update minimum data
+    let mut minimum_data = MinimumReserve::unpack_unchecked(&minimum_acc.data.borrow())?;
+    minimum_data.mint = *mint_acc.key;
+    minimum_data.is_initialized = true;
+    minimum_data.minimum = minimum;
+    MinimumReserve::pack(minimum_data, &mut minimum_acc.data.borrow_mut())?;
+
+    Ok(())
+  }
+
+  ///
+  /// Utilities
+  ///
+  pub fn is_program(program_id: &Pubkey, accounts: &[&AccountInfo]) -> ProgramResult {
+    for acc in &mut accounts.iter() {
+      if acc.owner != program_id {
+        return Err(AppError::IncorrectProgramId.into());
+      }
+    }
+    Ok(())
+  }
+
+  pub fn is_signer(accounts: &[&AccountInfo]) -> ProgramResult {
+    for acc in &mut accounts.iter() {
+      if !acc.is_signer {
+        return Err(AppError::InvalidOwner.into());
+      }
+    }
+    Ok(())
+  }
+
+    pub fn is_pool_owner(
+        owner: &AccountInfo,
+        pool_acc: &AccountInfo,
+        program_id: &Pubkey,
+        signer_accs: &[AccountInfo],
+    ) -> ProgramResult {
+        let pool_data = Pool::unpack(&pool_acc.data.borrow())?;
+        if pool_data.owner != *owner.key {
+          return Err(AppError::InvalidOwner.into());
+        }
+
+        // A multisig owner needs m of its members to sign, otherwise the owner itself signs
+        if owner.owner == program_id && owner.data_len() == Multisig::LEN {
+          let multisig_data = Multisig::unpack(&owner.data.borrow())?;
+          if multisig_data.count_signers(signer_accs) < multisig_data.m {
+            return Err(AppError::InvalidOwner.into());
+          }
+          return Ok(());
+        }
+        Self::is_signer(&[owner])
+    }
+
+    // The token programs come from the caller, anything but the real ones could fake a burn
+    pub fn is_token_programs(splt_program: &AccountInfo, splt_2022_program: &AccountInfo) -> ProgramResult {
+        if *splt_program.key != spl_token::id() || *splt_2022_program.key != spl_token_2022::id() {
+          return Err(AppError::IncorrectProgramId.into());
+        }
+        Ok(())
+    }
+
+    // A token account read for its content, the owner is checked against the real token programs
+    // since an account of any other program could carry whatever balance it likes
+    pub fn token_account(acc: &AccountInfo) -> Result<Account, ProgramError> {
+        if *acc.owner != spl_token::id() && *acc.owner != spl_token_2022::id() {
+          return Err(AppError::IncorrectProgramId.into());
+        }
+        Account::unpack_base(&acc.data.borrow())
+    }
+
+    // Amounts valued in the underlying through the cached rates of their tokens, which must be fresh
+    pub fn scale_amounts(
+        pool_data: &Pool,
+        amounts: &[u64],
+        now: i64,
+        scale: fn(u64, u64) -> Option<u64>,
+    ) -> Result<Vec<u64>, ProgramError> {
+        let mut scaled_amounts = Vec::with_capacity(amounts.len());
+        for (i, &amount) in amounts.iter().enumerate() {
+          let rate = pool_data.get_rate(i, now).ok_or(AppError::ExpiredRate)?;
+          scaled_amounts.push(scale(amount, rate).ok_or(AppError::Overflow)?);
+        }
+        Ok(scaled_amounts)
+    }
+
+    // Initial reserves under the mint's MinimumReserve, or the default of its decimals, are dust
+    pub fn is_minimum_reserve(
+        reserve: u64,
+        minimum_acc: &AccountInfo,
+        mint_acc: &AccountInfo,
+        program_id: &Pubkey,
+    ) -> ProgramResult {
+        let (minimum_key, _) = Pubkey::find_program_address(
+          &[&mint_acc.key.to_bytes(), MINIMUM_RESERVE_SEED],
+          program_id,
+        );
+        if *minimum_acc.key != minimum_key {
+          return Err(AppError::InvalidOwner.into());
+        }
+        let minimum = if *minimum_acc.owner == *program_id {
+          MinimumReserve::unpack(&minimum_acc.data.borrow())?.minimum
+        } else {
+          let (mint_data, _) = Mint::unpack_with_extensions(&mint_acc.data.borrow())?;
+          Pool::minimum_reserve(mint_data.decimals)
+        };
+        if reserve < minimum {
+          return Err(AppError::BelowMinimumReserve.into());
+        }
+        Ok(())
+    }
+
+    // Balance of the discount mint held by the trader
+    pub fn holder_balance(
+        discount_acc: &AccountInfo,
+        payer: &AccountInfo,
+        discount_mint: &Pubkey,
+    ) -> Result<u64, ProgramError> {
+        let discount_data = Self::token_account(discount_acc)?;
+        if discount_data.mint != *discount_mint {
+          return Err(AppError::InvalidMint.into());
+        }
+        if discount_data.owner != *payer.key {
+          return Err(AppError::InvalidOwner.into());
+        }
+        Ok(discount_data.amount)
+    }
+
+    // Legacy and Token-2022 mints live side by side, pick the program owning the mint
+    pub fn token_program<'a, 'b>(
+        mint_acc: &AccountInfo<'a>,
+        splt_programs: &[&'b AccountInfo<'a>],
+    ) -> Result<&'b AccountInfo<'a>, ProgramError> {
+        for splt_program in splt_programs.iter() {
+          if *splt_program.key == *mint_acc.owner {
+            return Ok(splt_program);
+          }
+        }
+        Err(AppError::IncorrectProgramId.into())
+    }
+
+    // Move tokens into a treasury and return the amount it received after transfer fees
+    pub fn deposit<'a>(
+        amount: u64,
+        src_acc: &AccountInfo<'a>,
+        mint_acc: &AccountInfo<'a>,
+        treasury_acc: &AccountInfo<'a>,
+        owner: &AccountInfo<'a>,
+        splt_programs: &[&AccountInfo<'a>],
+    ) -> Result<u64, ProgramError> {
+        let splt_program = Self::token_program(mint_acc, splt_programs)?;
+        let (mint_data, _) = Mint::unpack_with_extensions(&mint_acc.data.borrow())?;
+        XSPLT::transfer_checked(
+          amount,
+          mint_data.decimals,
+          src_acc,
+          mint_acc,
+          treasury_acc,
+          owner,
+          splt_program,
+          &[],
+        )?;
+        Self::post_fee_amount(amount, mint_acc, splt_programs)
+    }
+
+    pub fn withdraw<'a>(
+        amount: u64,
+        treasury_acc: &AccountInfo<'a>,
+        mint_acc: &AccountInfo<'a>,
+        dst_acc: &AccountInfo<'a>,
+        treasurer: &AccountInfo<'a>,
+        splt_programs: &[&AccountInfo<'a>],
+        seed: &[&[&[u8]]],
+    ) -> ProgramResult {
+        let splt_program = Self::token_program(mint_acc, splt_programs)?;
+        let (mint_data, _) = Mint::unpack_with_extensions(&mint_acc.data.borrow())?;
+        XSPLT::transfer_checked(
+          amount,
+          mint_data.decimals,
+          treasury_acc,
+          mint_acc,
+          dst_acc,
+          treasurer,
+          splt_program,
+          seed,
+        )
+    }
+
+    // Send out the whole balance of a treasurer-owned account, donations included, then close it
+    pub fn sweep_account<'a>(
+        src_acc: &AccountInfo<'a>,
+        mint_acc: &AccountInfo<'a>,
+        dst_token_acc: &AccountInfo<'a>,
+        dst_acc: &AccountInfo<'a>,
+        treasurer: &AccountInfo<'a>,
+        splt_programs: &[&AccountInfo<'a>],
+        seed: &[&[&[u8]]],
+    ) -> ProgramResult {
+        let splt_program = Self::token_program(mint_acc, splt_programs)?;
+        let amount = Self::token_account(src_acc)?.amount;
+        if amount != 0 {
+          Self::withdraw(amount, src_acc, mint_acc, dst_token_acc, treasurer, splt_programs, seed)?;
+        }
+        // Token-2022 refuses to close an account still withholding transfer fees
+        let (_, transfer_fee_config) = Mint::unpack_with_extensions(&mint_acc.data.borrow())?;
+        if transfer_fee_config.is_some() {
+          XSPLT::harvest_withheld_tokens_to_mint(mint_acc, src_acc, splt_program)?;
+        }
+        XSPLT::close_account(src_acc, dst_acc, treasurer, splt_program, seed)
+    }
+
+    // The amount a treasury receives when amount is sent
+    pub fn post_fee_amount<'a>(
+        amount: u64,
+        mint_acc: &AccountInfo<'a>,
+        splt_programs: &[&AccountInfo<'a>],
+    ) -> Result<u64, ProgramError> {
+        Self::token_program(mint_acc, splt_programs)?;
+        let (_, transfer_fee_config) = Mint::unpack_with_extensions(&mint_acc.data.borrow())?;
+        let fee = match transfer_fee_config {
+          Some(transfer_fee_config) => transfer_fee_config
+            .get_epoch_fee(Clock::get()?.epoch)
+            .calculate_fee(amount)
+            .ok_or(AppError::Overflow)?,
+          None => 0,
+        };
+        Ok(amount.checked_sub(fee).ok_or(AppError::Overflow)?)
+    }
+
+    // The amount to send so that a treasury receives post_fee_amount
+    pub fn pre_fee_amount<'a>(
+        post_fee_amount: u64,
+        mint_acc: &AccountInfo<'a>,
+        splt_programs: &[&AccountInfo<'a>],
+    ) -> Result<u64, ProgramError> {
+        Self::token_program(mint_acc, splt_programs)?;
+        let (_, transfer_fee_config) = Mint::unpack_with_extensions(&mint_acc.data.borrow())?;
+        match transfer_fee_config {
+          Some(transfer_fee_config) => Ok(transfer_fee_config
+            .get_epoch_fee(Clock::get()?.epoch)
+            .calculate_pre_fee_amount(post_fee_amount)
+            .ok_or(AppError::Overflow)?),
+          None => Ok(post_fee_amount),
+        }
+    }
+
+    // Create the wallet's associated account when the destination doesn't exist yet
+    pub fn ensure_account<'a>(
+        dst_acc: &AccountInfo<'a>,
+        wallet_acc: &AccountInfo<'a>,
+        payer: &AccountInfo<'a>,
+        mint_acc: &AccountInfo<'a>,
+        system_program: &AccountInfo<'a>,
+        splt_programs: &[&AccountInfo<'a>],
+        splata_program: &AccountInfo<'a>,
+    ) -> ProgramResult {
+        if !dst_acc.data_is_empty() {
+          return Ok(());
+        }
+        XSPLATA::initialize_account_idempotent(
+          payer,
+          dst_acc,
+          wallet_acc,
+          mint_acc,
+          system_program,
+          Self::token_program(mint_acc, splt_programs)?,
+          splata_program,
+          &[],
+        )
+    }
+
+    // Optional accounts may be left off the tail, or passed as the program id to skip them
+    pub fn next_optional_account_info<'a, 'b>(
+        accounts_iter: &mut Iter<'b, AccountInfo<'a>>,
+        program_id: &Pubkey,
+    ) -> Option<&'b AccountInfo<'a>> {
+        accounts_iter.next().filter(|acc| *acc.key != *program_id)
+    }
+
+    // Look for another top-level swap of this pool going the other way in the transaction.
+    // Swaps made through CPI don't show in the instructions sysvar, so a program wrapping both legs
+    // of a sandwich in CPIs isn't caught
+    pub fn has_opposite_swap(
+        pool_acc: &AccountInfo,
+        treasury_bid_acc: &AccountInfo,
+        treasury_ask_acc: &AccountInfo,
+        instructions_acc: &AccountInfo,
+        current_index: usize,
+        program_id: &Pubkey,
+    ) -> Result<bool, ProgramError> {
+        let mut index = 0;
+        while let Ok(ix) = load_instruction_at_checked(index, instructions_acc) {
+          index += 1;
+          if index - 1 == current_index
+            || ix.program_id != *program_id
+            || ix.data.first() != Some(&SWAP_TAG)
+            || ix.accounts.len() <= SWAP_TREASURY_ASK_INDEX
+          {
+            continue;
+          }
+          if ix.accounts[SWAP_POOL_INDEX].pubkey == *pool_acc.key
+            && ix.accounts[SWAP_TREASURY_BID_INDEX].pubkey == *treasury_ask_acc.key
+            && ix.accounts[SWAP_TREASURY_ASK_INDEX].pubkey == *treasury_bid_acc.key
+          {
+            return Ok(true);
+          }
+        }
+        Ok(false)
+    }
+
+    // A wSOL leg moves native SOL when a system wallet stands in for the token account.
+    // A token account that doesn't exist yet looks just the same, so the signer's associated
+    // wSOL account is always handled as a token account. A missing associated wSOL account of
+    // someone else, e.g. the swap recipient, is paid as a wallet: the SOL lands on its address
+    // and becomes the wSOL balance once the associated account is created there
+    pub fn is_native(mint_acc: &AccountInfo, wallet_acc: &AccountInfo, signer: &AccountInfo) -> bool {
+        if *mint_acc.key != native_mint::id()
+          || *wallet_acc.owner != system_program::id()
+          || !wallet_acc.data_is_empty()
+        {
+          return false;
+        }
+        let (associated_key, _) = Pubkey::find_program_address(
+          &[&signer.key.to_bytes(), &spl_token::id().to_bytes(), &native_mint::id().to_bytes()],
+          &spl_associated_token_account::id(),
+        );
+        *wallet_acc.key != associated_key
+    }
+
+    // Associated token account of the wallet, under the program owning the mint
+    pub fn associated_address(wallet_acc: &AccountInfo, mint_acc: &AccountInfo) -> Pubkey {
+        let (associated_key, _) = Pubkey::find_program_address(
+          &[&wallet_acc.key.to_bytes(), &mint_acc.owner.to_bytes(), &mint_acc.key.to_bytes()],
+          &spl_associated_token_account::id(),
+        );
+        associated_key
+    }
+
+    // The temporary wSOL account trailing the fixed accounts when a leg is native
+    pub fn next_native_account<'a, 'b>(
+        accounts_iter: &mut Iter<'b, AccountInfo<'a>>,
+        is_native: bool,
+    ) -> Result<Option<&'b AccountInfo<'a>>, ProgramError> {
+        if !is_native {
+          return Ok(None);
+        }
+        let wsol_acc = next_account_info(accounts_iter)?; // fresh keypair, closed in the same instruction
+        Ok(Some(wsol_acc))
+    }
+
+    // Open a temporary wSOL account owned by the wallet and fund it with amount lamports
+    pub fn wrap_native<'a>(
+        amount: u64,
+        wallet_acc: &AccountInfo<'a>,
+        wsol_acc: &AccountInfo<'a>,
+        mint_acc: &AccountInfo<'a>,
+        system_program: &AccountInfo<'a>,
+        splt_program: &AccountInfo<'a>,
+    ) -> ProgramResult {
+        invoke(
+          &system_instruction::create_account(
+            wallet_acc.key,
+            wsol_acc.key,
+            Rent::get()?.minimum_balance(ACCOUNT_LEN),
+            ACCOUNT_LEN as u64,
+            splt_program.key,
+          ),
+          &[wallet_acc.clone(), wsol_acc.clone(), system_program.clone()],
+        )?;
+        XSPLT::initialize_account3(wsol_acc, mint_acc, wallet_acc, splt_program)?;
+        if amount != 0 {
+          invoke(
+            &system_instruction::transfer(wallet_acc.key, wsol_acc.key, amount),
+            &[wallet_acc.clone(), wsol_acc.clone(), system_program.clone()],
+          )?;
+          XSPLT::sync_native(wsol_acc, splt_program, &[])?;
+        }
+        Ok(())
+    }
+
+    // Deposit lamports straight from a wallet through a temporary wSOL account
+    pub fn deposit_native<'a>(
+        amount: u64,
+        wallet_acc: &AccountInfo<'a>,
+        wsol_acc: &AccountInfo<'a>,
+        mint_acc: &AccountInfo<'a>,
+        treasury_acc: &AccountInfo<'a>,
+        system_program: &AccountInfo<'a>,
+        splt_programs: &[&AccountInfo<'a>],
+    ) -> Result<u64, ProgramError> {
+        let splt_program = Self::token_program(mint_acc, splt_programs)?;
+        Self::wrap_native(amount, wallet_acc, wsol_acc, mint_acc, system_program, splt_program)?;
+        let received_amount =
+          Self::deposit(amount, wsol_acc, mint_acc, treasury_acc, wallet_acc, splt_programs)?;
+        XSPLT::close_account(wsol_acc, wallet_acc, wallet_acc, splt_program, &[])?;
+        Ok(received_amount)
+    }
+
+    // Withdraw to a wallet as lamports, the payer fronts the temporary wSOL account and forwards the amount
+    #[allow(clippy::too_many_arguments)]
+    pub fn withdraw_native<'a>(
+        amount: u64,
+        treasury_acc: &AccountInfo<'a>,
+        mint_acc: &AccountInfo<'a>,
+        wallet_acc: &AccountInfo<'a>,
+        wsol_acc: &AccountInfo<'a>,
+        payer: &AccountInfo<'a>,
+        system_program: &AccountInfo<'a>,
+        treasurer: &AccountInfo<'a>,
+        splt_programs: &[&AccountInfo<'a>],
+        seed: &[&[&[u8]]],
+    ) -> ProgramResult {
+        let splt_program = Self::token_program(mint_acc, splt_programs)?;
+        Self::wrap_native(0, payer, wsol_acc, mint_acc, system_program, splt_program)?;
+        Self::withdraw(amount, treasury_acc, mint_acc, wsol_acc, treasurer, splt_programs, seed)?;
+        XSPLT::close_account(wsol_acc, payer, payer, splt_program, &[])?;
+        if *wallet_acc.key != *payer.key {
+          invoke(
+            &system_instruction::transfer(payer.key, wallet_acc.key, amount),
+            &[payer.clone(), wallet_acc.clone(), system_program.clone()],
+          )?;
+        }
+        Ok(())
+    }
+
+    pub fn safe_seed(
+        seed_acc: &AccountInfo,
+        expected_acc: &AccountInfo,
+        program_id: &Pubkey,
+    ) -> Result<[u8; 32], PubkeyError> {
+        let seed: [u8; 32] = seed_acc.key.to_bytes();
+        let key = Pubkey::create_program_address(&[&seed], program_id)?;
+        if key != *expected_acc.key {
+          return Err(PubkeyError::InvalidSeeds);
+        }
+        Ok(seed)
+    }
+
+  pub fn key_xor(
+    left_key: &Pubkey,
+    right_key: &Pubkey,
+  ) -> Result<Pubkey, PubkeyError> {
+    let left_key_bytes: [u8; 32] = left_key.to_bytes();
+    let right_key_bytes: [u8; 32] = right_key.to_bytes();
+    let mut xor_key_bytes: [u8; 32] = [0; 32];   
+
+    for i in 0..32 {
+      xor_key_bytes[i] = left_key_bytes[i] ^ right_key_bytes[i];
+    }
+
+    let xor_key = Pubkey::new_from_array(xor_key_bytes);
+    
+    Ok(xor_key)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::schema::account::AccountState;
+  use solana_program::sysvar::instructions::{
+    self, construct_instructions_data, store_current_index, BorrowedAccountMeta, BorrowedInstruction,
+  };
+
+  fn token_data(mint: &Pubkey, owner: &Pubkey, amount: u64) -> Vec<u8> {
+    let mut data = vec![0u8; Account::LEN];
+    let account = Account {
+      mint: *mint,
+      owner: *owner,
+      amount,
+      state: AccountState::Initialized,
+      ..Account::default()
+    };
+    Account::pack(account, &mut data).unwrap();
+    data
+  }
+
+  #[test]
+  fn test_holder_balance() {
+    let discount_mint = Pubkey::new_unique();
+    let payer_key = Pubkey::new_unique();
+    let discount_key = Pubkey::new_unique();
+    let token_program = spl_token::id();
+    let system = system_program::id();
+    let (mut payer_lamports, mut discount_lamports) = (0, 0);
+    let mut payer_data = vec![];
+    let mut discount_data = token_data(&discount_mint, &payer_key, 1_000_000);
+    let payer = AccountInfo::new(
+      &payer_key, true, true, &mut payer_lamports, &mut payer_data, &system, false, 0,
+    );
+    let discount_acc = AccountInfo::new(
+      &discount_key, false, false, &mut discount_lamports, &mut discount_data, &token_program, false, 0,
+    );
+    assert_eq!(
+      Processor::holder_balance(&discount_acc, &payer, &discount_mint),
+      Ok(1_000_000)
+    );
+    assert_eq!(
+      Processor::holder_balance(&discount_acc, &payer, &Pubkey::new_unique()),
+      Err(AppError::InvalidMint.into())
+    );
+  }
+
+  #[test]
+  fn test_holder_balance_forged_account() {
+    let discount_mint = Pubkey::new_unique();
+    let payer_key = Pubkey::new_unique();
+    let discount_key = Pubkey::new_unique();
+    // Same bytes as a real token account, but owned by some other program
+    let forger = Pubkey::new_unique();
+    let system = system_program::id();
+    let (mut payer_lamports, mut discount_lamports) = (0, 0);
+    let mut payer_data = vec![];
+    let mut discount_data = token_data(&discount_mint, &payer_key, u64::MAX);
+    let payer = AccountInfo::new(
+      &payer_key, true, true, &mut payer_lamports, &mut payer_data, &system, false, 0,
+    );
+    let discount_acc = AccountInfo::new(
+      &discount_key, false, false, &mut discount_lamports, &mut discount_data, &forger, false, 0,
+    );
+    assert_eq!(
+      Processor::holder_balance(&discount_acc, &payer, &discount_mint),
+      Err(AppError::IncorrectProgramId.into())
+    );
+  }
+
+  fn mint_data(decimals: u8) -> Vec<u8> {
+    let mut data = vec![0u8; Mint::LEN];
+    let mint = Mint {
+      decimals,
+      is_initialized: true,
+      ..Mint::default()
+    };
+    Mint::pack(mint, &mut data).unwrap();
+    data
+  }
+
+  #[test]
+  fn test_minimum_reserve_default() {
+    let program_id = Pubkey::new_unique();
+    let mint_key = Pubkey::new_unique();
+    let (minimum_key, _) =
+      Pubkey::find_program_address(&[&mint_key.to_bytes(), MINIMUM_RESERVE_SEED], &program_id);
+    let token_program = spl_token::id();
+    let system = system_program::id();
+    let (mut mint_lamports, mut minimum_lamports) = (0, 0);
+    let mut mint_data = mint_data(9);
+    let mut minimum_data = vec![];
+    let mint_acc = AccountInfo::new(
+      &mint_key, false, false, &mut mint_lamports, &mut mint_data, &token_program, false, 0,
+    );
+    let minimum_acc = AccountInfo::new(
+      &minimum_key, false, false, &mut minimum_lamports, &mut minimum_data, &system, false, 0,
+    );
+    assert!(Processor::is_minimum_reserve(1_000_000, &minimum_acc, &mint_acc, &program_id).is_ok());
+    assert_eq!(
+      Processor::is_minimum_reserve(999_999, &minimum_acc, &mint_acc, &program_id),
+      Err(AppError::BelowMinimumReserve.into())
+    );
+    // Mints with few decimals only need a unit
+    assert_eq!(Pool::minimum_reserve(0), 1);
+    assert_eq!(Pool::minimum_reserve(3), 1);
+    assert_eq!(Pool::minimum_reserve(6), 1000);
+  }
+
+  #[test]
+  fn test_minimum_reserve_override() {
+    let program_id = Pubkey::new_unique();
+    let mint_key = Pubkey::new_unique();
+    let (minimum_key, _) =
+      Pubkey::find_program_address(&[&mint_key.to_bytes(), MINIMUM_RESERVE_SEED], &program_id);
+    let token_program = spl_token::id();
+    let (mut mint_lamports, mut minimum_lamports) = (0, 0);
+    let mut mint_data = mint_data(0);
+    let mut minimum_data = vec![0u8; MinimumReserve::LEN];
+    let minimum = MinimumReserve {
+      mint: mint_key,
+      is_initialized: true,
+      minimum: 50,
+    };
+    MinimumReserve::pack(minimum, &mut minimum_data).unwrap();
+    let mint_acc = AccountInfo::new(
+      &mint_key, false, false, &mut mint_lamports, &mut mint_data, &token_program, false, 0,
+    );
+    let minimum_acc = AccountInfo::new(
+      &minimum_key, false, false, &mut minimum_lamports, &mut minimum_data, &program_id, false, 0,
+    );
+    assert!(Processor::is_minimum_reserve(50, &minimum_acc, &mint_acc, &program_id).is_ok());
+    assert_eq!(
+      Processor::is_minimum_reserve(49, &minimum_acc, &mint_acc, &program_id),
+      Err(AppError::BelowMinimumReserve.into())
+    );
+  }
+
+  #[test]
+  fn test_minimum_reserve_forged_account() {
+    let program_id = Pubkey::new_unique();
+    let mint_key = Pubkey::new_unique();
+    // A lower minimum at any other address is ignored
+    let forged_key = Pubkey::new_unique();
+    let token_program = spl_token::id();
+    let (mut mint_lamports, mut minimum_lamports) = (0, 0);
+    let mut mint_data = mint_data(9);
+    let mut minimum_data = vec![0u8; MinimumReserve::LEN];
+    let minimum = MinimumReserve {
+      mint: mint_key,
+      is_initialized: true,
+      minimum: 1,
+    };
+    MinimumReserve::pack(minimum, &mut minimum_data).unwrap();
+    let mint_acc = AccountInfo::new(
+      &mint_key, false, false, &mut mint_lamports, &mut mint_data, &token_program, false, 0,
+    );
+    let minimum_acc = AccountInfo::new(
+      &forged_key, false, false, &mut minimum_lamports, &mut minimum_data, &program_id, false, 0,
+    );
+    assert_eq!(
+      Processor::is_minimum_reserve(1, &minimum_acc, &mint_acc, &program_id),
+      Err(AppError::InvalidOwner.into())
+    );
+  }
+
+  #[test]
+  fn test_is_native() {
+    let payer_key = Pubkey::new_unique();
+    let wallet_key = Pubkey::new_unique();
+    let (associated_key, _) = Pubkey::find_program_address(
+      &[&payer_key.to_bytes(), &spl_token::id().to_bytes(), &native_mint::id().to_bytes()],
+      &spl_associated_token_account::id(),
+    );
+    let native_key = native_mint::id();
+    let other_mint_key = Pubkey::new_unique();
+    let token_program = spl_token::id();
+    let system = system_program::id();
+    let mut lamports = [0u64; 5];
+    let [payer_lamports, wallet_lamports, associated_lamports, native_lamports, other_lamports] =
+      &mut lamports;
+    let (mut payer_data, mut wallet_data, mut associated_data) = (vec![], vec![], vec![]);
+    let (mut native_data, mut other_data) = (mint_data(9), mint_data(9));
+    let payer = AccountInfo::new(
+      &payer_key, true, true, payer_lamports, &mut payer_data, &system, false, 0,
+    );
+    let wallet_acc = AccountInfo::new(
+      &wallet_key, false, true, wallet_lamports, &mut wallet_data, &system, false, 0,
+    );
+    // The payer's wSOL account, not created yet
+    let associated_acc = AccountInfo::new(
+      &associated_key, false, true, associated_lamports, &mut associated_data, &system, false, 0,
+    );
+    let native_acc = AccountInfo::new(
+      &native_key, false, false, native_lamports, &mut native_data, &token_program, false, 0,
+    );
+    let other_mint_acc = AccountInfo::new(
+      &other_mint_key, false, false, other_lamports, &mut other_data, &token_program, false, 0,
+    );
+    assert!(Processor::is_native(&native_acc, &wallet_acc, &payer));
+    assert!(Processor::is_native(&native_acc, &payer, &payer));
+    assert!(!Processor::is_native(&native_acc, &associated_acc, &payer));
+    assert!(!Processor::is_native(&other_mint_acc, &wallet_acc, &payer));
+  }
+
+  fn swap_ix_data(
+    program_id: &Pubkey,
+    pool: &Pubkey,
+    treasury_bid: &Pubkey,
+    treasury_ask: &Pubkey,
+  ) -> (Pubkey, Vec<Pubkey>, Vec<u8>) {
+    let mut keys: Vec<Pubkey> = (0..=SWAP_TREASURY_ASK_INDEX).map(|_| Pubkey::new_unique()).collect();
+    keys[SWAP_POOL_INDEX] = *pool;
+    keys[SWAP_TREASURY_BID_INDEX] = *treasury_bid;
+    keys[SWAP_TREASURY_ASK_INDEX] = *treasury_ask;
+    let mut data = vec![SWAP_TAG];
+    data.extend_from_slice(&[0u8; 16]);
+    (*program_id, keys, data)
+  }
+
+  fn instructions_data(ixs: &[(Pubkey, Vec<Pubkey>, Vec<u8>)], current_index: u16) -> Vec<u8> {
+    let borrowed: Vec<BorrowedInstruction> = ixs
+      .iter()
+      .map(|(program_id, keys, data)| BorrowedInstruction {
+        program_id,
+        accounts: keys
+          .iter()
+          .map(|pubkey| BorrowedAccountMeta { pubkey, is_signer: false, is_writable: true })
+          .collect(),
+        data,
+      })
+      .collect();
+    let mut data = construct_instructions_data(&borrowed);
+    store_current_index(&mut data, current_index);
+    data
+  }
+
+  #[test]
+  fn test_has_opposite_swap() {
+    let program_id = Pubkey::new_unique();
+    let pool_key = Pubkey::new_unique();
+    let treasury_0_key = Pubkey::new_unique();
+    let treasury_1_key = Pubkey::new_unique();
+    let sysvar_key = instructions::id();
+    let system = system_program::id();
+    let mut lamports = [0u64; 4];
+    let [pool_lamports, treasury_0_lamports, treasury_1_lamports, sysvar_lamports] = &mut lamports;
+    let (mut pool_data, mut treasury_0_data, mut treasury_1_data) = (vec![], vec![], vec![]);
+    let pool_acc = AccountInfo::new(
+      &pool_key, false, true, pool_lamports, &mut pool_data, &program_id, false, 0,
+    );
+    let treasury_0_acc = AccountInfo::new(
+      &treasury_0_key, false, true, treasury_0_lamports, &mut treasury_0_data, &system, false, 0,
+    );
+    let treasury_1_acc = AccountInfo::new(
+      &treasury_1_key, false, true, treasury_1_lamports, &mut treasury_1_data, &system, false, 0,
+    );
+    // Token 0 -> token 1, then token 1 -> token 0 right after
+    let front = swap_ix_data(&program_id, &pool_key, &treasury_0_key, &treasury_1_key);
+    let back = swap_ix_data(&program_id, &pool_key, &treasury_1_key, &treasury_0_key);
+    let mut sysvar_data = instructions_data(&[front.clone(), back], 1);
+    let instructions_acc = AccountInfo::new(
+      &sysvar_key, false, false, sysvar_lamports, &mut sysvar_data, &system, false, 0,
+    );
+    let current_index = load_current_index_checked(&instructions_acc).unwrap() as usize;
+    assert_eq!(
+      Processor::has_opposite_swap(
+        &pool_acc,
+        &treasury_1_acc,
+        &treasury_0_acc,
+        &instructions_acc,
+        current_index,
+        &program_id,
+      ),
+      Ok(true)
+    );
+    // Two swaps the same way are fine
+    let mut lamports = 0;
+    let mut sysvar_data = instructions_data(&[front.clone(), front], 1);
+    let instructions_acc = AccountInfo::new(
+      &sysvar_key, false, false, &mut lamports, &mut sysvar_data, &system, false, 0,
+    );
+    assert_eq!(
+      Processor::has_opposite_swap(
+        &pool_acc,
+        &treasury_0_acc,
+        &treasury_1_acc,
+        &instructions_acc,
+        1,
+        &program_id,
+      ),
+      Ok(false)
+    );
   }
-}
\ No newline at end of file
+}
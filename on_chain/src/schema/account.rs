@@ -0,0 +1,122 @@
+use crate::schema::mint::{pack_coption_key, unpack_coption_key};
+use arrayref::{array_mut_ref, array_ref, array_refs, mut_array_refs};
+use num_enum::TryFromPrimitive;
+use solana_program::{
+  program_error::ProgramError,
+  program_option::COption,
+  program_pack::{IsInitialized, Pack, Sealed},
+  pubkey::Pubkey,
+};
+
+///
+/// Account state
+///
+#[repr(u8)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, TryFromPrimitive)]
+pub enum AccountState {
+  #[default]
+  Uninitialized,
+  Initialized,
+  Frozen,
+}
+
+///
+/// Token account struct
+///
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Account {
+  pub mint: Pubkey,
+  pub owner: Pubkey,
+  pub amount: u64,
+  pub delegate: COption<Pubkey>,
+  pub state: AccountState,
+  pub is_native: COption<u64>,
+  pub delegated_amount: u64,
+  pub close_authority: COption<Pubkey>,
+}
+
+impl Account {
+  // Read a legacy or a Token-2022 account, extensions past the base layout are ignored
+  pub fn unpack_base(src: &[u8]) -> Result<Self, ProgramError> {
+    if src.len() < Self::LEN {
+      return Err(ProgramError::InvalidAccountData);
+    }
+    let account = Self::unpack_from_slice(&src[..Self::LEN])?;
+    if !account.is_initialized() {
+      return Err(ProgramError::UninitializedAccount);
+    }
+    Ok(account)
+  }
+}
+
+impl Sealed for Account {}
+
+impl IsInitialized for Account {
+  fn is_initialized(&self) -> bool {
+    self.state != AccountState::Uninitialized
+  }
+}
+
+impl Pack for Account {
+  const LEN: usize = 165;
+
+  fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
+    let src = array_ref![src, 0, 165];
+    let (mint, owner, amount, delegate, state, is_native, delegated_amount, close_authority) =
+      array_refs![src, 32, 32, 8, 36, 1, 12, 8, 36];
+    Ok(Account {
+      mint: Pubkey::new_from_array(*mint),
+      owner: Pubkey::new_from_array(*owner),
+      amount: u64::from_le_bytes(*amount),
+      delegate: unpack_coption_key(delegate)?,
+      state: AccountState::try_from_primitive(state[0]).or(Err(ProgramError::InvalidAccountData))?,
+      is_native: unpack_coption_u64(is_native)?,
+      delegated_amount: u64::from_le_bytes(*delegated_amount),
+      close_authority: unpack_coption_key(close_authority)?,
+    })
+  }
+
+  fn pack_into_slice(&self, dst: &mut [u8]) {
+    let dst = array_mut_ref![dst, 0, 165];
+    let (
+      mint_dst,
+      owner_dst,
+      amount_dst,
+      delegate_dst,
+      state_dst,
+      is_native_dst,
+      delegated_amount_dst,
+      close_authority_dst,
+    ) = mut_array_refs![dst, 32, 32, 8, 36, 1, 12, 8, 36];
+    mint_dst.copy_from_slice(self.mint.as_ref());
+    owner_dst.copy_from_slice(self.owner.as_ref());
+    *amount_dst = self.amount.to_le_bytes();
+    pack_coption_key(&self.delegate, delegate_dst);
+    state_dst[0] = self.state as u8;
+    pack_coption_u64(&self.is_native, is_native_dst);
+    *delegated_amount_dst = self.delegated_amount.to_le_bytes();
+    pack_coption_key(&self.close_authority, close_authority_dst);
+  }
+}
+
+fn pack_coption_u64(src: &COption<u64>, dst: &mut [u8; 12]) {
+  let (tag, body) = mut_array_refs![dst, 4, 8];
+  match src {
+    COption::Some(amount) => {
+      *tag = [1, 0, 0, 0];
+      *body = amount.to_le_bytes();
+    }
+    COption::None => {
+      *tag = [0; 4];
+    }
+  }
+}
+fn unpack_coption_u64(src: &[u8; 12]) -> Result<COption<u64>, ProgramError> {
+  let (tag, body) = array_refs![src, 4, 8];
+  match *tag {
+    [0, 0, 0, 0] => Ok(COption::None),
+    [1, 0, 0, 0] => Ok(COption::Some(u64::from_le_bytes(*body))),
+    _ => Err(ProgramError::InvalidAccountData),
+  }
+}
@@ -0,0 +1,61 @@
+use num_enum::TryFromPrimitive;
+use solana_program::program_error::ProgramError;
+
+///
+/// Account type, the first byte of every program account besides the pool
+///
+#[repr(u8)]
+#[derive(Clone, Copy, Debug, PartialEq, TryFromPrimitive)]
+pub enum AccountType {
+  Uninitialized,
+  Multisig,
+  Allowlist,
+  RouterRegistry,
+  TraderVolume,
+  RateProvider,
+  MinimumReserve,
+}
+
+impl AccountType {
+  // Accounts of the same size can't pass for one another, only a blank account may lack its tag
+  pub fn check(tag: &[u8; 1], expected: AccountType, is_initialized: bool) -> Result<(), ProgramError> {
+    match AccountType::try_from_primitive(tag[0]) {
+      Ok(account_type) if account_type == expected => Ok(()),
+      Ok(AccountType::Uninitialized) if !is_initialized => Ok(()),
+      _ => Err(ProgramError::InvalidAccountData),
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::schema::multisig::Multisig;
+  use solana_program::program_pack::Pack;
+
+  #[test]
+  fn test_check() {
+    assert!(AccountType::check(&[1], AccountType::Multisig, true).is_ok());
+    assert!(AccountType::check(&[0], AccountType::Multisig, false).is_ok());
+    assert!(AccountType::check(&[0], AccountType::Multisig, true).is_err());
+    assert!(AccountType::check(&[2], AccountType::Multisig, true).is_err());
+    assert!(AccountType::check(&[255], AccountType::Multisig, false).is_err());
+  }
+
+  #[test]
+  fn test_multisig_tag() {
+    let multisig = Multisig {
+      m: 1,
+      n: 1,
+      is_initialized: true,
+      ..Multisig::default()
+    };
+    let mut data = vec![0u8; Multisig::LEN];
+    Multisig::pack(multisig, &mut data).unwrap();
+    assert_eq!(data[0], AccountType::Multisig as u8);
+    assert_eq!(Multisig::unpack(&data).unwrap(), multisig);
+    // Same bytes under another tag are not a multisig
+    data[0] = AccountType::Allowlist as u8;
+    assert!(Multisig::unpack(&data).is_err());
+  }
+}
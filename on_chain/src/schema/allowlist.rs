@@ -0,0 +1,104 @@
+use crate::schema::account_type::AccountType;
+use arrayref::{array_mut_ref, array_ref, array_refs, mut_array_refs};
+use solana_program::{
+  program_error::ProgramError,
+  program_pack::{IsInitialized, Pack, Sealed},
+  pubkey::Pubkey,
+};
+
+pub const MAX_TRADERS: usize = 32;
+
+///
+/// Allowlist struct
+///
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Allowlist {
+  pub pool: Pubkey,
+  pub earning: u64, // protocol earning charged to the listed traders, over oracle DECIMALS
+  pub is_initialized: bool,
+  pub count: u8,
+  pub traders: [Pubkey; MAX_TRADERS],
+}
+
+impl Allowlist {
+  pub fn contains(&self, trader: &Pubkey) -> bool {
+    self.traders[0..self.count as usize].contains(trader)
+  }
+
+  pub fn add(&mut self, trader: &Pubkey) -> bool {
+    if self.count as usize >= MAX_TRADERS || self.contains(trader) {
+      return false;
+    }
+    self.traders[self.count as usize] = *trader;
+    self.count += 1;
+    true
+  }
+
+  // Swap the last trader into the freed slot, the order of the list doesn't matter
+  pub fn remove(&mut self, trader: &Pubkey) -> bool {
+    let count = self.count as usize;
+    match self.traders[0..count].iter().position(|item| *item == *trader) {
+      Some(position) => {
+        self.traders[position] = self.traders[count - 1];
+        self.traders[count - 1] = Pubkey::default();
+        self.count -= 1;
+        true
+      }
+      None => false,
+    }
+  }
+}
+
+impl Sealed for Allowlist {}
+
+impl IsInitialized for Allowlist {
+  fn is_initialized(&self) -> bool {
+    self.is_initialized
+  }
+}
+
+impl Pack for Allowlist {
+  const LEN: usize = 1 + 32 + 8 + 1 + 1 + 32 * MAX_TRADERS;
+
+  fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
+    let src = array_ref![src, 0, 1067];
+    let (account_type, pool, earning, is_initialized, count, traders_flat) =
+      array_refs![src, 1, 32, 8, 1, 1, 32 * MAX_TRADERS];
+    let is_initialized = match is_initialized {
+      [0] => false,
+      [1] => true,
+      _ => return Err(ProgramError::InvalidAccountData),
+    };
+    AccountType::check(account_type, AccountType::Allowlist, is_initialized)?;
+    if count[0] as usize > MAX_TRADERS {
+      return Err(ProgramError::InvalidAccountData);
+    }
+    let mut traders = [Pubkey::default(); MAX_TRADERS];
+    for (i, trader) in traders.iter_mut().enumerate() {
+      *trader = Pubkey::new_from_array(*array_ref![traders_flat, i * 32, 32]);
+    }
+    Ok(Allowlist {
+      pool: Pubkey::new_from_array(*pool),
+      earning: u64::from_le_bytes(*earning),
+      is_initialized,
+      count: count[0],
+      traders,
+    })
+  }
+
+  fn pack_into_slice(&self, dst: &mut [u8]) {
+    let dst = array_mut_ref![dst, 0, 1067];
+    let (account_type_dst, pool_dst, earning_dst, is_initialized_dst, count_dst, traders_flat_dst) =
+      mut_array_refs![dst, 1, 32, 8, 1, 1, 32 * MAX_TRADERS];
+    account_type_dst[0] = AccountType::Allowlist as u8;
+    pool_dst.copy_from_slice(self.pool.as_ref());
+    *earning_dst = self.earning.to_le_bytes();
+    is_initialized_dst[0] = self.is_initialized as u8;
+    count_dst[0] = self.count;
+    for (i, trader) in self.traders.iter().enumerate() {
+      let trader_dst = array_mut_ref![traders_flat_dst, i * 32, 32];
+      trader_dst.copy_from_slice(trader.as_ref());
+    }
+  }
+}
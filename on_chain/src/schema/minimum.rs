@@ -0,0 +1,79 @@
+use crate::schema::account_type::AccountType;
+use arrayref::{array_mut_ref, array_ref, array_refs, mut_array_refs};
+use solana_program::{
+  program_error::ProgramError,
+  program_pack::{IsInitialized, Pack, Sealed},
+  pubkey::Pubkey,
+};
+
+// The minimum reserve of a mint lives at the address derived from [mint, MINIMUM_RESERVE_SEED]
+pub const MINIMUM_RESERVE_SEED: &[u8] = b"minimum_reserve";
+
+///
+/// Minimum reserve struct, set by the mint authority to override the default of its decimals
+///
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct MinimumReserve {
+  pub mint: Pubkey,
+  pub is_initialized: bool,
+  pub minimum: u64,
+}
+
+impl Sealed for MinimumReserve {}
+
+impl IsInitialized for MinimumReserve {
+  fn is_initialized(&self) -> bool {
+    self.is_initialized
+  }
+}
+
+impl Pack for MinimumReserve {
+  const LEN: usize = 1 + 32 + 1 + 8;
+
+  fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
+    let src = array_ref![src, 0, 42];
+    let (account_type, mint, is_initialized, minimum) = array_refs![src, 1, 32, 1, 8];
+    let is_initialized = match is_initialized {
+      [0] => false,
+      [1] => true,
+      _ => return Err(ProgramError::InvalidAccountData),
+    };
+    AccountType::check(account_type, AccountType::MinimumReserve, is_initialized)?;
+    Ok(MinimumReserve {
+      mint: Pubkey::new_from_array(*mint),
+      is_initialized,
+      minimum: u64::from_le_bytes(*minimum),
+    })
+  }
+
+  fn pack_into_slice(&self, dst: &mut [u8]) {
+    let dst = array_mut_ref![dst, 0, 42];
+    let (account_type_dst, mint_dst, is_initialized_dst, minimum_dst) =
+      mut_array_refs![dst, 1, 32, 1, 8];
+    account_type_dst[0] = AccountType::MinimumReserve as u8;
+    mint_dst.copy_from_slice(self.mint.as_ref());
+    is_initialized_dst[0] = self.is_initialized as u8;
+    *minimum_dst = self.minimum.to_le_bytes();
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_pack() {
+    let minimum_reserve = MinimumReserve {
+      mint: Pubkey::new_unique(),
+      is_initialized: true,
+      minimum: 1000,
+    };
+    let mut data = vec![0u8; MinimumReserve::LEN];
+    MinimumReserve::pack(minimum_reserve, &mut data).unwrap();
+    assert_eq!(data[0], AccountType::MinimumReserve as u8);
+    assert_eq!(MinimumReserve::unpack(&data).unwrap(), minimum_reserve);
+    data[0] = AccountType::Multisig as u8;
+    assert!(MinimumReserve::unpack(&data).is_err());
+  }
+}
@@ -1,3 +1,4 @@
+use crate::error::AppError;
 use arrayref::{array_mut_ref, array_ref, array_refs, mut_array_refs};
 use solana_program::{
   program_error::ProgramError,
@@ -6,6 +7,132 @@ use solana_program::{
   pubkey::Pubkey,
 };
 
+// Wrapped SOL, a token account of this mint carries its balance as lamports
+pub mod native_mint {
+  solana_program::declare_id!("So11111111111111111111111111111111111111112");
+}
+
+pub mod spl_token {
+  solana_program::declare_id!("TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA");
+}
+
+pub mod spl_token_2022 {
+  solana_program::declare_id!("TokenzQdBNbLqP5VEhdkAS6EPFLC1PHnBqCXEpPxuEb");
+}
+
+pub mod spl_associated_token_account {
+  solana_program::declare_id!("ATokenGPvbdGVxr1b2hvZbsiqW5xWH25efTNsLJA8knL");
+}
+
+// Token-2022 pads mints to the account length, then writes the account type
+// and the extensions as (type: u16, length: u16, value) entries
+pub const ACCOUNT_LEN: usize = 165;
+const ACCOUNT_TYPE_MINT: u8 = 1;
+
+const EXTENSION_UNINITIALIZED: u16 = 0;
+const EXTENSION_TRANSFER_FEE_CONFIG: u16 = 1;
+// Extensions that neither move nor lock the pool funds
+const SAFE_EXTENSIONS: [u16; 8] = [
+  3,  // MintCloseAuthority
+  10, // InterestBearingConfig
+  18, // MetadataPointer
+  19, // TokenMetadata
+  20, // GroupPointer
+  21, // TokenGroup
+  22, // GroupMemberPointer
+  23, // TokenGroupMember
+];
+
+///
+/// Transfer fee
+///
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct TransferFee {
+  pub epoch: u64,
+  pub maximum_fee: u64,
+  pub transfer_fee_basis_points: u16,
+}
+
+impl TransferFee {
+  const MAX_BASIS_POINTS: u128 = 10000;
+
+  fn unpack(src: &[u8; 18]) -> Self {
+    let (epoch, maximum_fee, transfer_fee_basis_points) = array_refs![src, 8, 8, 2];
+    TransferFee {
+      epoch: u64::from_le_bytes(*epoch),
+      maximum_fee: u64::from_le_bytes(*maximum_fee),
+      transfer_fee_basis_points: u16::from_le_bytes(*transfer_fee_basis_points),
+    }
+  }
+
+  // fee = min(ceil(amount * bps / 10000), maximum_fee)
+  pub fn calculate_fee(&self, amount: u64) -> Option<u64> {
+    let bps = self.transfer_fee_basis_points as u128;
+    if bps == 0 || amount == 0 {
+      return Some(0);
+    }
+    let fee = (amount as u128)
+      .checked_mul(bps)?
+      .checked_add(Self::MAX_BASIS_POINTS - 1)?
+      .checked_div(Self::MAX_BASIS_POINTS)? as u64;
+    Some(fee.min(self.maximum_fee))
+  }
+
+  // The smallest amount to send so that the receiver gets at least post_fee_amount
+  pub fn calculate_pre_fee_amount(&self, post_fee_amount: u64) -> Option<u64> {
+    let bps = self.transfer_fee_basis_points as u128;
+    if bps == 0 || post_fee_amount == 0 {
+      return Some(post_fee_amount);
+    }
+    if bps == Self::MAX_BASIS_POINTS {
+      return post_fee_amount.checked_add(self.maximum_fee);
+    }
+    let denominator = Self::MAX_BASIS_POINTS - bps;
+    let pre_fee_amount = (post_fee_amount as u128)
+      .checked_mul(Self::MAX_BASIS_POINTS)?
+      .checked_add(denominator - 1)?
+      .checked_div(denominator)? as u64;
+    if pre_fee_amount.checked_sub(post_fee_amount)? > self.maximum_fee {
+      return post_fee_amount.checked_add(self.maximum_fee);
+    }
+    Some(pre_fee_amount)
+  }
+}
+
+///
+/// Transfer fee config extension
+///
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct TransferFeeConfig {
+  pub older_transfer_fee: TransferFee,
+  pub newer_transfer_fee: TransferFee,
+}
+
+impl TransferFeeConfig {
+  const LEN: usize = 32 + 32 + 8 + 18 + 18;
+
+  fn unpack(src: &[u8]) -> Result<Self, ProgramError> {
+    if src.len() != Self::LEN {
+      return Err(ProgramError::InvalidAccountData);
+    }
+    let src = array_ref![src, 0, 108];
+    let (_authority, _withdraw_withheld_authority, _withheld_amount, older, newer) =
+      array_refs![src, 32, 32, 8, 18, 18];
+    Ok(TransferFeeConfig {
+      older_transfer_fee: TransferFee::unpack(older),
+      newer_transfer_fee: TransferFee::unpack(newer),
+    })
+  }
+
+  pub fn get_epoch_fee(&self, epoch: u64) -> &TransferFee {
+    if epoch >= self.newer_transfer_fee.epoch {
+      &self.newer_transfer_fee
+    } else {
+      &self.older_transfer_fee
+    }
+  }
+}
+
 ///
 /// Mint struct
 ///
@@ -19,6 +146,50 @@ pub struct Mint {
   pub freeze_authority: COption<Pubkey>,
 }
 
+impl Mint {
+  // Read a legacy or a Token-2022 mint, and reject the extensions the pool can't handle safely
+  pub fn unpack_with_extensions(
+    src: &[u8],
+  ) -> Result<(Self, Option<TransferFeeConfig>), ProgramError> {
+    if src.len() < Self::LEN {
+      return Err(ProgramError::InvalidAccountData);
+    }
+    let mint = Self::unpack_from_slice(&src[..Self::LEN])?;
+    if !mint.is_initialized() {
+      return Err(ProgramError::UninitializedAccount);
+    }
+    if src.len() == Self::LEN {
+      return Ok((mint, None));
+    }
+    if src.len() <= ACCOUNT_LEN || src[ACCOUNT_LEN] != ACCOUNT_TYPE_MINT {
+      return Err(ProgramError::InvalidAccountData);
+    }
+
+    let mut transfer_fee_config = None;
+    let mut offset = ACCOUNT_LEN + 1;
+    while offset + 4 <= src.len() {
+      let (extension_type, length) = array_refs![array_ref![src, offset, 4], 2, 2];
+      let extension_type = u16::from_le_bytes(*extension_type);
+      let length = u16::from_le_bytes(*length) as usize;
+      if extension_type == EXTENSION_UNINITIALIZED {
+        break;
+      }
+      let value = src
+        .get(offset + 4..offset + 4 + length)
+        .ok_or(ProgramError::InvalidAccountData)?;
+      match extension_type {
+        EXTENSION_TRANSFER_FEE_CONFIG => {
+          transfer_fee_config = Some(TransferFeeConfig::unpack(value)?)
+        }
+        extension_type if SAFE_EXTENSIONS.contains(&extension_type) => {}
+        _ => return Err(AppError::UnsupportedMintExtension.into()),
+      }
+      offset += 4 + length;
+    }
+    Ok((mint, transfer_fee_config))
+  }
+}
+
 impl Sealed for Mint {}
 
 impl IsInitialized for Mint {
@@ -69,7 +240,7 @@ impl Pack for Mint {
   }
 }
 
-fn pack_coption_key(src: &COption<Pubkey>, dst: &mut [u8; 36]) {
+pub(crate) fn pack_coption_key(src: &COption<Pubkey>, dst: &mut [u8; 36]) {
   let (tag, body) = mut_array_refs![dst, 4, 32];
   match src {
     COption::Some(key) => {
@@ -81,7 +252,7 @@ fn pack_coption_key(src: &COption<Pubkey>, dst: &mut [u8; 36]) {
     }
   }
 }
-fn unpack_coption_key(src: &[u8; 36]) -> Result<COption<Pubkey>, ProgramError> {
+pub(crate) fn unpack_coption_key(src: &[u8; 36]) -> Result<COption<Pubkey>, ProgramError> {
   let (tag, body) = array_refs![src, 4, 32];
   match *tag {
     [0, 0, 0, 0] => Ok(COption::None),
@@ -89,3 +260,122 @@ fn unpack_coption_key(src: &[u8; 36]) -> Result<COption<Pubkey>, ProgramError> {
     _ => Err(ProgramError::InvalidAccountData),
   }
 }
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn mint_data(extensions: &[(u16, Vec<u8>)]) -> Vec<u8> {
+    let mint = Mint {
+      supply: 1000,
+      decimals: 6,
+      is_initialized: true,
+      ..Mint::default()
+    };
+    let mut data = vec![0u8; Mint::LEN];
+    Mint::pack(mint, &mut data).unwrap();
+    if extensions.is_empty() {
+      return data;
+    }
+    data.resize(ACCOUNT_LEN, 0);
+    data.push(ACCOUNT_TYPE_MINT);
+    for (extension_type, value) in extensions {
+      data.extend_from_slice(&extension_type.to_le_bytes());
+      data.extend_from_slice(&(value.len() as u16).to_le_bytes());
+      data.extend_from_slice(value);
+    }
+    data
+  }
+
+  fn transfer_fee_config_data(older: TransferFee, newer: TransferFee) -> Vec<u8> {
+    let mut data = vec![0u8; 32 + 32 + 8];
+    for fee in [older, newer] {
+      data.extend_from_slice(&fee.epoch.to_le_bytes());
+      data.extend_from_slice(&fee.maximum_fee.to_le_bytes());
+      data.extend_from_slice(&fee.transfer_fee_basis_points.to_le_bytes());
+    }
+    data
+  }
+
+  #[test]
+  fn test_calculate_fee() {
+    let fee = TransferFee {
+      epoch: 0,
+      maximum_fee: 50,
+      transfer_fee_basis_points: 100,
+    };
+    // 1% rounded up, within the maximum
+    assert_eq!(fee.calculate_fee(0), Some(0));
+    assert_eq!(fee.calculate_fee(101), Some(2));
+    assert_eq!(fee.calculate_fee(1000), Some(10));
+    assert_eq!(fee.calculate_fee(1_000_000), Some(50));
+    // The pre-fee amount delivers at least the post-fee amount
+    assert_eq!(fee.calculate_pre_fee_amount(0), Some(0));
+    assert_eq!(fee.calculate_pre_fee_amount(990), Some(1000));
+    assert_eq!(fee.calculate_pre_fee_amount(1_000_000), Some(1_000_050));
+    for post_fee_amount in [1, 99, 990, 4950, 4951, 123_456] {
+      let pre_fee_amount = fee.calculate_pre_fee_amount(post_fee_amount).unwrap();
+      let received = pre_fee_amount - fee.calculate_fee(pre_fee_amount).unwrap();
+      assert!(received >= post_fee_amount);
+      let short = pre_fee_amount - 1;
+      assert!(short - fee.calculate_fee(short).unwrap() < post_fee_amount);
+    }
+    let full = TransferFee {
+      transfer_fee_basis_points: 10000,
+      ..fee
+    };
+    assert_eq!(full.calculate_pre_fee_amount(100), Some(150));
+  }
+
+  #[test]
+  fn test_unpack_with_extensions() {
+    // Legacy mints carry no extension
+    let (mint, config) = Mint::unpack_with_extensions(&mint_data(&[])).unwrap();
+    assert_eq!((mint.supply, mint.decimals, config), (1000, 6, None));
+
+    let older = TransferFee {
+      epoch: 0,
+      maximum_fee: 10,
+      transfer_fee_basis_points: 50,
+    };
+    let newer = TransferFee {
+      epoch: 5,
+      maximum_fee: 20,
+      transfer_fee_basis_points: 100,
+    };
+    let data = mint_data(&[
+      (3, vec![0u8; 32]),
+      (EXTENSION_TRANSFER_FEE_CONFIG, transfer_fee_config_data(older, newer)),
+    ]);
+    let (mint, config) = Mint::unpack_with_extensions(&data).unwrap();
+    assert_eq!(mint.supply, 1000);
+    let config = config.unwrap();
+    assert_eq!(config.get_epoch_fee(4), &older);
+    assert_eq!(config.get_epoch_fee(5), &newer);
+
+    // Trailing zeroes end the extensions
+    let mut padded = data.clone();
+    padded.extend_from_slice(&[0u8; 8]);
+    assert!(Mint::unpack_with_extensions(&padded).is_ok());
+
+    // Transfer hooks and the like are rejected
+    let data = mint_data(&[(14, vec![0u8; 64])]);
+    assert_eq!(
+      Mint::unpack_with_extensions(&data).unwrap_err(),
+      AppError::UnsupportedMintExtension.into()
+    );
+    // So are truncated extensions, wrong account types and uninitialized mints
+    let mut truncated = mint_data(&[(3, vec![0u8; 32])]);
+    truncated.truncate(truncated.len() - 1);
+    assert!(Mint::unpack_with_extensions(&truncated).is_err());
+    let mut account = mint_data(&[(3, vec![0u8; 32])]);
+    account[ACCOUNT_LEN] = 2;
+    assert!(Mint::unpack_with_extensions(&account).is_err());
+    let mut uninitialized = mint_data(&[]);
+    uninitialized[45] = 0;
+    assert_eq!(
+      Mint::unpack_with_extensions(&uninitialized).unwrap_err(),
+      ProgramError::UninitializedAccount
+    );
+  }
+}
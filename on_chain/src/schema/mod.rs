@@ -1,2 +1,11 @@
+pub mod account;
+pub mod account_type;
+pub mod allowlist;
 pub mod mint;
+pub mod minimum;
+pub mod multisig;
 pub mod pool;
+pub mod price;
+pub mod rate;
+pub mod registry;
+pub mod volume;
@@ -0,0 +1,88 @@
+use crate::schema::account_type::AccountType;
+use arrayref::{array_mut_ref, array_ref, array_refs, mut_array_refs};
+use solana_program::{
+  account_info::AccountInfo,
+  program_error::ProgramError,
+  program_pack::{IsInitialized, Pack, Sealed},
+  pubkey::Pubkey,
+};
+
+pub const MIN_SIGNERS: usize = 1;
+pub const MAX_SIGNERS: usize = 11;
+
+///
+/// Multisig struct
+///
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Multisig {
+  pub m: u8,
+  pub n: u8,
+  pub is_initialized: bool,
+  pub signers: [Pubkey; MAX_SIGNERS],
+}
+
+impl Multisig {
+  // Count the distinct members that signed, each member is counted once
+  pub fn count_signers(&self, accounts: &[AccountInfo]) -> u8 {
+    let mut matched = [false; MAX_SIGNERS];
+    let mut num_signers = 0;
+    for acc in accounts.iter() {
+      for (position, signer) in self.signers[0..self.n as usize].iter().enumerate() {
+        if *signer == *acc.key && acc.is_signer && !matched[position] {
+          matched[position] = true;
+          num_signers += 1;
+        }
+      }
+    }
+    num_signers
+  }
+}
+
+impl Sealed for Multisig {}
+
+impl IsInitialized for Multisig {
+  fn is_initialized(&self) -> bool {
+    self.is_initialized
+  }
+}
+
+impl Pack for Multisig {
+  const LEN: usize = 1 + 1 + 1 + 1 + 32 * MAX_SIGNERS;
+
+  fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
+    let src = array_ref![src, 0, 356];
+    let (account_type, m, n, is_initialized, signers_flat) =
+      array_refs![src, 1, 1, 1, 1, 32 * MAX_SIGNERS];
+    let is_initialized = match is_initialized {
+      [0] => false,
+      [1] => true,
+      _ => return Err(ProgramError::InvalidAccountData),
+    };
+    AccountType::check(account_type, AccountType::Multisig, is_initialized)?;
+    let mut signers = [Pubkey::default(); MAX_SIGNERS];
+    for (i, signer) in signers.iter_mut().enumerate() {
+      *signer = Pubkey::new_from_array(*array_ref![signers_flat, i * 32, 32]);
+    }
+    Ok(Multisig {
+      m: m[0],
+      n: n[0],
+      is_initialized,
+      signers,
+    })
+  }
+
+  fn pack_into_slice(&self, dst: &mut [u8]) {
+    let dst = array_mut_ref![dst, 0, 356];
+    let (account_type_dst, m_dst, n_dst, is_initialized_dst, signers_flat_dst) =
+      mut_array_refs![dst, 1, 1, 1, 1, 32 * MAX_SIGNERS];
+    account_type_dst[0] = AccountType::Multisig as u8;
+    m_dst[0] = self.m;
+    n_dst[0] = self.n;
+    is_initialized_dst[0] = self.is_initialized as u8;
+    for (i, signer) in self.signers.iter().enumerate() {
+      let signer_dst = array_mut_ref![signers_flat_dst, i * 32, 32];
+      signer_dst.copy_from_slice(signer.as_ref());
+    }
+  }
+}
@@ -7,50 +7,297 @@ use solana_program::{
   pubkey::Pubkey,
 };
 
-const MAX_TOKEN_COUNT: u8 = 6;
+pub const MAX_TOKEN_COUNT: usize = 6;
+pub const MINIMUM_LIQUIDITY: u64 = 1000;
+pub const LOCKER_SEED: &[u8] = b"locker";
+// Half of the swap earning at most, over the oracle decimals
+pub const MAX_REFERRAL_SHARE: u64 = 500000000;
+pub const FEE_TIER_COUNT: usize = 4;
+pub const HOLDER_TIER_COUNT: usize = 4;
+// A tenth of the traded amount at most, over the oracle decimals
+pub const MAX_DYNAMIC_FEE: u64 = 100000000;
+pub const MAX_DEVIATION_BPS: u16 = 10000;
+pub const MAX_TRADE_RATIO_BPS: u16 = 10000;
+// A 1:1 rate, over the oracle decimals
+pub const RATE_ONE: u64 = 1000000000;
+// The whole fee at most, over the oracle decimals
+pub const MAX_FEE_DISCOUNT: u64 = 1000000000;
+
+const HEADER_LEN: usize = 32 + 1 + 32 + 32;
+const TOKEN_LEN: usize = 32 + 32 + 8;
+const VAULT_LEN: usize = 32;
+const PAUSE_LEN: usize = 1;
+const FEE_TIER_LEN: usize = 8 + 8;
+const HOLDER_TIER_LEN: usize = 8 + 8;
+const DYNAMIC_FEE_LEN: usize = 1 + 8 + 8 + 8 + 8;
+const CIRCUIT_BREAKER_LEN: usize = 8 + MAX_TOKEN_COUNT * 8 + 2;
+const TRADE_LIMITS_LEN: usize = 2 + 2;
+const ANTI_SANDWICH_LEN: usize = 1;
+const PRICE_PEG_LEN: usize = 32 + 8 + 2 + 2;
+const RATE_LEN: usize = 32 + 8 + 8 + 8;
+const RATES_LEN: usize = MAX_TOKEN_COUNT * RATE_LEN;
+const CURVE_LEN: usize = 1;
+const EARNING_LEN: usize = 1;
+const RETIRE_LEN: usize = 1 + 8 + 8;
+const LAST_RESERVES_LEN: usize = MAX_TOKEN_COUNT * 8;
+// Everything after the token slots came later, so the original layout keeps its offsets
+const SETTINGS_LEN: usize = PAUSE_LEN
+  + 8
+  + FEE_TIER_COUNT * FEE_TIER_LEN
+  + 32
+  + HOLDER_TIER_COUNT * HOLDER_TIER_LEN
+  + DYNAMIC_FEE_LEN
+  + CIRCUIT_BREAKER_LEN
+  + TRADE_LIMITS_LEN
+  + ANTI_SANDWICH_LEN
+  + PRICE_PEG_LEN
+  + RATES_LEN
+  + CURVE_LEN
+  + EARNING_LEN
+  + RETIRE_LEN
+  + LAST_RESERVES_LEN;
+const POOL_LEN: usize =
+  HEADER_LEN + MAX_TOKEN_COUNT * TOKEN_LEN + MAX_TOKEN_COUNT * VAULT_LEN + SETTINGS_LEN;
+
+///
+/// Pause flags
+///
+pub const PAUSE_SWAP: u8 = 1 << 0;
+pub const PAUSE_DEPOSIT: u8 = 1 << 1;
+pub const PAUSE_WITHDRAW: u8 = 1 << 2;
+pub const PAUSE_ALL: u8 = PAUSE_SWAP | PAUSE_DEPOSIT | PAUSE_WITHDRAW;
+// Set by FreezePool and cleared by ThawPool only, it pauses everything on top of the other flags
+pub const PAUSE_FROZEN: u8 = 1 << 3;
 
 ///
 /// Pool state
 ///
 #[repr(u8)]
-#[derive(Clone, Copy, Debug, PartialEq, TryFromPrimitive)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, TryFromPrimitive)]
 pub enum PoolState {
+  #[default]
   Uninitialized,
   Initialized,
-  Frozen,
+  WithdrawOnly,
+  Deprecated,
 }
-impl Default for PoolState {
-  fn default() -> Self {
-    PoolState::Uninitialized
-  }
+
+///
+/// Fee tier, traders whose rolling volume reaches `volume` get `discount` off the fee
+/// The volume counts the traded notional in token 0
+///
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct FeeTier {
+  pub volume: u64,
+  pub discount: u64,
 }
 
+///
+/// Holder tier, traders holding `balance` of the discount mint get `discount` off the earning
+///
 #[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct HolderTier {
+  pub balance: u64,
+  pub discount: u64,
+}
+
+///
+/// Pricing curve
+///
+#[repr(u8)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, TryFromPrimitive)]
+pub enum Curve {
+  #[default]
+  Product,
+  ConstantSum,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
 pub struct Pool {
   pub owner: Pubkey,
   pub state: PoolState,
+  pub pause: u8,
   pub mint_lpt: Pubkey,
-  pub vault: Pubkey,
 
-  pub mints: Vec<Pubkey>,  
+  pub mints: Vec<Pubkey>,
   pub treasurys: Vec<Pubkey>,
   pub reserves: Vec<u64>,
+  pub vaults: Vec<Pubkey>, // protocol earnings of every token, in the mint they were charged in
+
+  pub referral_share: u64,
+  pub fee_tiers: Vec<FeeTier>,
+  pub discount_mint: Pubkey,
+  pub holder_tiers: Vec<HolderTier>,
+
+  pub is_dynamic_fee: bool,
+  pub min_fee: u64,
+  pub max_fee: u64,
+  pub volatility: u64, // decaying sum of the price changes, over the oracle decimals
+  pub last_slot: u64,
+
+  pub reference_slot: u64,
+  pub reference_reserves: Vec<u64>, // reserves before the first swap of reference_slot
+  // Basis points, 0 turns the circuit breaker off. A rejected swap can't leave state behind,
+  // so pausing the pool after a trip is left to a watcher calling TogglePause or FreezePool
+  pub max_deviation: u16,
+
+  pub max_bid_ratio: u16, // basis points of the bid reserve, 0 means no cap
+  pub max_ask_ratio: u16, // basis points of the ask reserve, 0 means no cap
+
+  pub is_anti_sandwich: bool,
+
+  pub price_acc: Pubkey,   // prices a whole token 1 in whole token 0 for swaps between them, default when unpegged
+  pub max_staleness: u64,  // slots
+  pub spread: u16,         // basis points
+  pub max_confidence: u16, // basis points of the price
+
+  pub rate_providers: Vec<Pubkey>, // default when the token has no provider
+  pub rates: Vec<u64>,
+  pub rate_expiries: Vec<i64>,
+  pub rate_durations: Vec<i64>, // seconds a provider rate stays valid after its update
+
+  pub curve: Curve,
+
+  // Earnings stay in the token they were charged in, so instead of an earning token to pick
+  // there is only the choice of taking them at all
+  pub is_earning: bool, // false to waive the protocol earning
+
+  // The retiring leg only leaves the pool, through exits at the LPT per token of its retirement.
+  // Token 0 can't retire, so 0 means no leg is retiring
+  pub retiring_index: u8,
+  pub retiring_lpt: u64,     // a leg's share of the LPT supply at the retirement
+  pub retiring_reserve: u64, // the leg's reserve at the retirement
+
+  // Reserves when last_slot opened, the volatility adds how far the spot prices moved since
+  pub last_reserves: Vec<u64>,
 }
 
 impl Pool {
-  pub fn is_frozen(&self) -> bool {
-    self.state == PoolState::Frozen
+  pub fn is_paused(&self, flags: u8) -> bool {
+    self.pause & (flags | PAUSE_FROZEN) != 0
+  }
+
+  pub fn is_active(&self) -> bool {
+    self.state == PoolState::Initialized
+  }
+
+  // Proportional exits stay open once the pool is winding down, whatever the pause flags
+  pub fn can_withdraw(&self) -> bool {
+    match self.state {
+      PoolState::Initialized => !self.is_paused(PAUSE_WITHDRAW),
+      PoolState::WithdrawOnly | PoolState::Deprecated => true,
+      PoolState::Uninitialized => false,
+    }
+  }
+
+  // Initialized <-> WithdrawOnly -> Deprecated, and Deprecated is final
+  pub fn can_transit(&self, state: PoolState) -> bool {
+    matches!(
+      (self.state, state),
+      (PoolState::Initialized, PoolState::WithdrawOnly)
+        | (PoolState::Initialized, PoolState::Deprecated)
+        | (PoolState::WithdrawOnly, PoolState::Initialized)
+        | (PoolState::WithdrawOnly, PoolState::Deprecated)
+    )
+  }
+
+  // A thousandth of a whole token, and at least one unit for mints with few decimals.
+  // Mints can override it with a MinimumReserve account
+  pub fn minimum_reserve(decimals: u8) -> u64 {
+    10u64
+      .checked_pow(decimals as u32)
+      .map(|unit| unit / 1000)
+      .unwrap_or(u64::MAX)
+      .max(1)
+  }
+
+  // The best discount among the tiers reached by the volume
+  pub fn fee_discount(&self, volume: u64) -> u64 {
+    self
+      .fee_tiers
+      .iter()
+      .filter(|tier| volume >= tier.volume)
+      .map(|tier| tier.discount)
+      .max()
+      .unwrap_or(0)
   }
-  
+
+  // The best discount among the tiers reached by the balance
+  pub fn holder_discount(&self, balance: u64) -> u64 {
+    self
+      .holder_tiers
+      .iter()
+      .filter(|tier| balance >= tier.balance)
+      .map(|tier| tier.discount)
+      .max()
+      .unwrap_or(0)
+  }
+
+  // Take the reserves as the reference price on the first swap of every slot
+  pub fn update_reference(&mut self, slot: u64) {
+    if self.reference_slot != slot {
+      self.reference_slot = slot;
+      self.reference_reserves = self.reserves.clone();
+    }
+  }
+
+  pub fn is_pegged(&self) -> bool {
+    self.price_acc != Pubkey::default()
+  }
+
+  // Cached rate of the token, 1:1 without a provider and None once expired
+  pub fn get_rate(&self, index: usize, now: i64) -> Option<u64> {
+    if self.rate_providers[index] == Pubkey::default() {
+      return Some(RATE_ONE);
+    }
+    if now > self.rate_expiries[index] {
+      return None;
+    }
+    Some(self.rates[index])
+  }
+
+  // Cache the provider rate until it is rate_duration old, a rate already past that isn't cached
+  pub fn cache_rate(&mut self, index: usize, rate: u64, updated_at: i64, now: i64) -> Option<()> {
+    let expiry = updated_at.checked_add(self.rate_durations[index])?;
+    if now > expiry {
+      return None;
+    }
+    self.rates[index] = rate;
+    self.rate_expiries[index] = expiry;
+    Some(())
+  }
+
+  pub fn retiring(&self) -> Option<usize> {
+    match self.retiring_index {
+      0 => None,
+      index => Some(index as usize),
+    }
+  }
+
+  pub fn token_count(&self) -> usize {
+    self
+      .mints
+      .iter()
+      .take_while(|&&mint| mint != Pubkey::default())
+      .count()
+  }
+
   pub fn get_reserve(&self, treasury: &Pubkey) -> Option<(u8, u64)> {
     for (index, &treasure_item) in self.treasurys.iter().enumerate() {
-      if treasure_item == *treasury {          
-          return Some((index, self.reserves[index]));
+      if treasure_item == *treasury {
+          return Some((index as u8, self.reserves[index]));
       }
     }
 
     None
   }
+
+  pub fn get_vault(&self, vault: &Pubkey) -> Option<u8> {
+    self.vaults[0..self.token_count()]
+      .iter()
+      .position(|item| *item == *vault)
+      .map(|index| index as u8)
+  }
 }
 
 impl Sealed for Pool {}
@@ -62,126 +309,381 @@ impl IsInitialized for Pool {
 }
 
 impl Pack for Pool {
-  const LEN: usize = 32 + 1 + 32 + 32 + MAX_TOKEN_COUNT * (32 + 32 + 8);
-  
+  const LEN: usize = POOL_LEN;
+
+  // Unpack data from [u8] to the data struct
   fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
     msg!("Pack-unpack_from_slice: read pool data");
-    let src = array_ref![src, 0, LEN];
+    let src = array_ref![src, 0, POOL_LEN];
+    let (header, tokens, vaults_flat, settings) = array_refs![
+      src,
+      HEADER_LEN,
+      MAX_TOKEN_COUNT * TOKEN_LEN,
+      MAX_TOKEN_COUNT * VAULT_LEN,
+      SETTINGS_LEN
+    ];
+    // The header vault predates the per-token vaults, it only mirrors the vault of token 0
+    let (owner, state, mint_lpt, _vault) = array_refs![header, 32, 1, 32, 32];
     let (
-      owner,
-      state,
-      mint_lpt,
-      vault,      
-    ) = array_refs![src, 32, 1, 32, 32];
-    
-    const preDataSize = 32 + 1 + 32 + 32;
-    const tokenDataSize = 32 + 32 + 8;
-    let mut mints = Vec::new();
-    let mut treasurys = Vec::new();
-    let mut reserves = Vec::new();
+      pause,
+      referral_share,
+      fee_tiers_flat,
+      discount_mint,
+      holder_tiers_flat,
+      dynamic_fee,
+      circuit_breaker,
+      trade_limits,
+      is_anti_sandwich,
+      price_peg,
+      rates_flat,
+      curve,
+      is_earning,
+      retire,
+      last_reserves_flat,
+    ) = array_refs![
+      settings,
+      PAUSE_LEN,
+      8,
+      FEE_TIER_COUNT * FEE_TIER_LEN,
+      32,
+      HOLDER_TIER_COUNT * HOLDER_TIER_LEN,
+      DYNAMIC_FEE_LEN,
+      CIRCUIT_BREAKER_LEN,
+      TRADE_LIMITS_LEN,
+      ANTI_SANDWICH_LEN,
+      PRICE_PEG_LEN,
+      RATES_LEN,
+      CURVE_LEN,
+      EARNING_LEN,
+      RETIRE_LEN,
+      LAST_RESERVES_LEN
+    ];
+    let (is_dynamic_fee, min_fee, max_fee, volatility, last_slot) =
+      array_refs![dynamic_fee, 1, 8, 8, 8, 8];
+    let is_dynamic_fee = match is_dynamic_fee {
+      [0] => false,
+      [1] => true,
+      _ => return Err(ProgramError::InvalidAccountData),
+    };
+
+    let mut mints = Vec::with_capacity(MAX_TOKEN_COUNT);
+    let mut treasurys = Vec::with_capacity(MAX_TOKEN_COUNT);
+    let mut reserves = Vec::with_capacity(MAX_TOKEN_COUNT);
+    let mut vaults = Vec::with_capacity(MAX_TOKEN_COUNT);
+    for i in 0..MAX_TOKEN_COUNT {
+      let token = array_ref![tokens, i * TOKEN_LEN, TOKEN_LEN];
+      let (mint, treasury, reserve) = array_refs![token, 32, 32, 8];
+      mints.push(Pubkey::new_from_array(*mint));
+      treasurys.push(Pubkey::new_from_array(*treasury));
+      reserves.push(u64::from_le_bytes(*reserve));
+      vaults.push(Pubkey::new_from_array(*array_ref![vaults_flat, i * VAULT_LEN, VAULT_LEN]));
+    }
 
+    let mut fee_tiers = Vec::with_capacity(FEE_TIER_COUNT);
+    for i in 0..FEE_TIER_COUNT {
+      let fee_tier = array_ref![fee_tiers_flat, i * FEE_TIER_LEN, FEE_TIER_LEN];
+      let (volume, discount) = array_refs![fee_tier, 8, 8];
+      fee_tiers.push(FeeTier {
+        volume: u64::from_le_bytes(*volume),
+        discount: u64::from_le_bytes(*discount),
+      });
+    }
+    let mut holder_tiers = Vec::with_capacity(HOLDER_TIER_COUNT);
+    for i in 0..HOLDER_TIER_COUNT {
+      let holder_tier = array_ref![holder_tiers_flat, i * HOLDER_TIER_LEN, HOLDER_TIER_LEN];
+      let (balance, discount) = array_refs![holder_tier, 8, 8];
+      holder_tiers.push(HolderTier {
+        balance: u64::from_le_bytes(*balance),
+        discount: u64::from_le_bytes(*discount),
+      });
+    }
+    let (reference_slot, reference_reserves_flat, max_deviation) =
+      array_refs![circuit_breaker, 8, MAX_TOKEN_COUNT * 8, 2];
+    let mut reference_reserves = Vec::with_capacity(MAX_TOKEN_COUNT);
+    for i in 0..MAX_TOKEN_COUNT {
+      reference_reserves.push(u64::from_le_bytes(*array_ref![reference_reserves_flat, i * 8, 8]));
+    }
+    let (max_bid_ratio, max_ask_ratio) = array_refs![trade_limits, 2, 2];
+    let is_anti_sandwich = match is_anti_sandwich {
+      [0] => false,
+      [1] => true,
+      _ => return Err(ProgramError::InvalidAccountData),
+    };
+    let (price_acc, max_staleness, spread, max_confidence) = array_refs![price_peg, 32, 8, 2, 2];
+    let is_earning = match is_earning {
+      [0] => false,
+      [1] => true,
+      _ => return Err(ProgramError::InvalidAccountData),
+    };
+    let mut rate_providers = Vec::with_capacity(MAX_TOKEN_COUNT);
+    let mut rates = Vec::with_capacity(MAX_TOKEN_COUNT);
+    let mut rate_expiries = Vec::with_capacity(MAX_TOKEN_COUNT);
+    let mut rate_durations = Vec::with_capacity(MAX_TOKEN_COUNT);
     for i in 0..MAX_TOKEN_COUNT {
-      let beginPos = preDataSize + i * tokenDataSize;
-      let token_data = &src[beginPos..beginPos + tokenDataSize];
-      let (mint, treasury, reserve) = array_refs![token_data, 32, 32, 8];
-
-      let mintPubKey = PubKey::new_from_array(*mint);
-      let treasuryPubKey = PubKey::new_from_array(*treasury);
-      let reservePubKey = PubKey::new_from_array(*reserve);
-    
-      mints.push(mintPubKey);
-      treasurys.push(treasuryPubKey);
-      reserves.push(reservePubKey);
-    }
-    
+      let token_rate = array_ref![rates_flat, i * RATE_LEN, RATE_LEN];
+      let (rate_provider, rate, rate_expiry, rate_duration) = array_refs![token_rate, 32, 8, 8, 8];
+      rate_providers.push(Pubkey::new_from_array(*rate_provider));
+      rates.push(u64::from_le_bytes(*rate));
+      rate_expiries.push(i64::from_le_bytes(*rate_expiry));
+      rate_durations.push(i64::from_le_bytes(*rate_duration));
+    }
+    let (retiring_index, retiring_lpt, retiring_reserve) = array_refs![retire, 1, 8, 8];
+    let mut last_reserves = Vec::with_capacity(MAX_TOKEN_COUNT);
+    for i in 0..MAX_TOKEN_COUNT {
+      last_reserves.push(u64::from_le_bytes(*array_ref![last_reserves_flat, i * 8, 8]));
+    }
+
     Ok(Pool {
       owner: Pubkey::new_from_array(*owner),
       state: PoolState::try_from_primitive(state[0]).or(Err(ProgramError::InvalidAccountData))?,
+      pause: pause[0],
       mint_lpt: Pubkey::new_from_array(*mint_lpt),
-      vault: Pubkey::new_from_array(*vault),
-      mints: mints,
-      treasurys: treasurys,
-      reserves: reserves,      
+      mints,
+      treasurys,
+      reserves,
+      vaults,
+      referral_share: u64::from_le_bytes(*referral_share),
+      fee_tiers,
+      discount_mint: Pubkey::new_from_array(*discount_mint),
+      holder_tiers,
+      is_dynamic_fee,
+      min_fee: u64::from_le_bytes(*min_fee),
+      max_fee: u64::from_le_bytes(*max_fee),
+      volatility: u64::from_le_bytes(*volatility),
+      last_slot: u64::from_le_bytes(*last_slot),
+      reference_slot: u64::from_le_bytes(*reference_slot),
+      reference_reserves,
+      max_deviation: u16::from_le_bytes(*max_deviation),
+      max_bid_ratio: u16::from_le_bytes(*max_bid_ratio),
+      max_ask_ratio: u16::from_le_bytes(*max_ask_ratio),
+      is_anti_sandwich,
+      price_acc: Pubkey::new_from_array(*price_acc),
+      max_staleness: u64::from_le_bytes(*max_staleness),
+      spread: u16::from_le_bytes(*spread),
+      max_confidence: u16::from_le_bytes(*max_confidence),
+      rate_providers,
+      rates,
+      rate_expiries,
+      rate_durations,
+      curve: Curve::try_from_primitive(curve[0]).or(Err(ProgramError::InvalidAccountData))?,
+      is_earning,
+      retiring_index: retiring_index[0],
+      retiring_lpt: u64::from_le_bytes(*retiring_lpt),
+      retiring_reserve: u64::from_le_bytes(*retiring_reserve),
+      last_reserves,
     })
   }
 
-  const LEN: usize = 32 + 1 + 32 + 32 + MAX_TOKEN_COUNT * (32 + 32 + 8 * 3);
-
-fn pack_into_slice(&self, dst: &mut [u8]) {
+  // Pack data from the data struct to [u8]
+  fn pack_into_slice(&self, dst: &mut [u8]) {
     msg!("Pack-pack_into_slice");
-    
-    // Initialization and packing for non-token data goes here...
+    let dst = array_mut_ref![dst, 0, POOL_LEN];
+    let (dst_header, dst_tokens, dst_vaults, dst_settings) = mut_array_refs![
+      dst,
+      HEADER_LEN,
+      MAX_TOKEN_COUNT * TOKEN_LEN,
+      MAX_TOKEN_COUNT * VAULT_LEN,
+      SETTINGS_LEN
+    ];
+    let (dst_owner, dst_state, dst_mint_lpt, dst_vault) = mut_array_refs![dst_header, 32, 1, 32, 32];
+    dst_owner.copy_from_slice(self.owner.as_ref());
+    *dst_state = [self.state as u8];
+    dst_mint_lpt.copy_from_slice(self.mint_lpt.as_ref());
+    dst_vault.copy_from_slice(self.vaults.first().copied().unwrap_or_default().as_ref());
+
+    for i in 0..MAX_TOKEN_COUNT {
+      let dst_token = array_mut_ref![dst_tokens, i * TOKEN_LEN, TOKEN_LEN];
+      let (dst_mint, dst_treasury, dst_reserve) = mut_array_refs![dst_token, 32, 32, 8];
+      let mint = self.mints.get(i).copied().unwrap_or_default();
+      let treasury = self.treasurys.get(i).copied().unwrap_or_default();
+      let reserve = self.reserves.get(i).copied().unwrap_or_default();
+      let vault = self.vaults.get(i).copied().unwrap_or_default();
+      dst_mint.copy_from_slice(mint.as_ref());
+      dst_treasury.copy_from_slice(treasury.as_ref());
+      *dst_reserve = reserve.to_le_bytes();
+      array_mut_ref![dst_vaults, i * VAULT_LEN, VAULT_LEN].copy_from_slice(vault.as_ref());
+    }
 
-    // Calculate the size of one token data
-    let token_data_size = 32 + 32 + 8;
+    let (
+      dst_pause,
+      dst_referral_share,
+      dst_fee_tiers,
+      dst_discount_mint,
+      dst_holder_tiers,
+      dst_dynamic_fee,
+      dst_circuit_breaker,
+      dst_trade_limits,
+      dst_is_anti_sandwich,
+      dst_price_peg,
+      dst_rates,
+      dst_curve,
+      dst_is_earning,
+      dst_retire,
+      dst_last_reserves,
+    ) = mut_array_refs![
+      dst_settings,
+      PAUSE_LEN,
+      8,
+      FEE_TIER_COUNT * FEE_TIER_LEN,
+      32,
+      HOLDER_TIER_COUNT * HOLDER_TIER_LEN,
+      DYNAMIC_FEE_LEN,
+      CIRCUIT_BREAKER_LEN,
+      TRADE_LIMITS_LEN,
+      ANTI_SANDWICH_LEN,
+      PRICE_PEG_LEN,
+      RATES_LEN,
+      CURVE_LEN,
+      EARNING_LEN,
+      RETIRE_LEN,
+      LAST_RESERVES_LEN
+    ];
+    *dst_pause = [self.pause];
+    *dst_referral_share = self.referral_share.to_le_bytes();
+    for i in 0..FEE_TIER_COUNT {
+      let dst_fee_tier = array_mut_ref![dst_fee_tiers, i * FEE_TIER_LEN, FEE_TIER_LEN];
+      let (dst_volume, dst_discount) = mut_array_refs![dst_fee_tier, 8, 8];
+      let fee_tier = self.fee_tiers.get(i).copied().unwrap_or_default();
+      *dst_volume = fee_tier.volume.to_le_bytes();
+      *dst_discount = fee_tier.discount.to_le_bytes();
+    }
+    dst_discount_mint.copy_from_slice(self.discount_mint.as_ref());
+    for i in 0..HOLDER_TIER_COUNT {
+      let dst_holder_tier = array_mut_ref![dst_holder_tiers, i * HOLDER_TIER_LEN, HOLDER_TIER_LEN];
+      let (dst_balance, dst_discount) = mut_array_refs![dst_holder_tier, 8, 8];
+      let holder_tier = self.holder_tiers.get(i).copied().unwrap_or_default();
+      *dst_balance = holder_tier.balance.to_le_bytes();
+      *dst_discount = holder_tier.discount.to_le_bytes();
+    }
 
-    // Loop over the tokens
+    let (dst_is_dynamic_fee, dst_min_fee, dst_max_fee, dst_volatility, dst_last_slot) =
+      mut_array_refs![dst_dynamic_fee, 1, 8, 8, 8, 8];
+    *dst_is_dynamic_fee = [self.is_dynamic_fee as u8];
+    *dst_min_fee = self.min_fee.to_le_bytes();
+    *dst_max_fee = self.max_fee.to_le_bytes();
+    *dst_volatility = self.volatility.to_le_bytes();
+    *dst_last_slot = self.last_slot.to_le_bytes();
+
+    let (dst_reference_slot, dst_reference_reserves, dst_max_deviation) =
+      mut_array_refs![dst_circuit_breaker, 8, MAX_TOKEN_COUNT * 8, 2];
+    *dst_reference_slot = self.reference_slot.to_le_bytes();
     for i in 0..MAX_TOKEN_COUNT {
-        // Calculate the start index for the i-th token data
-        let start = /* size of the data before the tokens */ + i * token_data_size;
-        let dst_token = array_mut_ref![dst, start, token_data_size];
+      let reference_reserve = self.reference_reserves.get(i).copied().unwrap_or_default();
+      *array_mut_ref![dst_reference_reserves, i * 8, 8] = reference_reserve.to_le_bytes();
+    }
+    *dst_max_deviation = self.max_deviation.to_le_bytes();
+
+    let (dst_max_bid_ratio, dst_max_ask_ratio) = mut_array_refs![dst_trade_limits, 2, 2];
+    *dst_max_bid_ratio = self.max_bid_ratio.to_le_bytes();
+    *dst_max_ask_ratio = self.max_ask_ratio.to_le_bytes();
+
+    *dst_is_anti_sandwich = [self.is_anti_sandwich as u8];
+
+    let (dst_price_acc, dst_max_staleness, dst_spread, dst_max_confidence) =
+      mut_array_refs![dst_price_peg, 32, 8, 2, 2];
+    dst_price_acc.copy_from_slice(self.price_acc.as_ref());
+    *dst_max_staleness = self.max_staleness.to_le_bytes();
+    *dst_spread = self.spread.to_le_bytes();
+    *dst_max_confidence = self.max_confidence.to_le_bytes();
+
+    for i in 0..MAX_TOKEN_COUNT {
+      let dst_token_rate = array_mut_ref![dst_rates, i * RATE_LEN, RATE_LEN];
+      let (dst_rate_provider, dst_rate, dst_rate_expiry, dst_rate_duration) =
+        mut_array_refs![dst_token_rate, 32, 8, 8, 8];
+      let rate_provider = self.rate_providers.get(i).copied().unwrap_or_default();
+      let rate = self.rates.get(i).copied().unwrap_or_default();
+      let rate_expiry = self.rate_expiries.get(i).copied().unwrap_or_default();
+      let rate_duration = self.rate_durations.get(i).copied().unwrap_or_default();
+      dst_rate_provider.copy_from_slice(rate_provider.as_ref());
+      *dst_rate = rate.to_le_bytes();
+      *dst_rate_expiry = rate_expiry.to_le_bytes();
+      *dst_rate_duration = rate_duration.to_le_bytes();
+    }
+
+    *dst_curve = [self.curve as u8];
 
-        // Break the token's destination slice into mint, treasury, and reserve
-        let (dst_mint, dst_treasury, dst_reserve) = mut_array_refs![dst_token, 32, 32, 8];
+    *dst_is_earning = [self.is_earning as u8];
 
-        // Retrieve the token data; we'll use placeholder values here
-        let mint = self.mints[i];
-        let treasury = self.treasuries[i];
-        let reserve = self.reserves[i];
+    let (dst_retiring_index, dst_retiring_lpt, dst_retiring_reserve) =
+      mut_array_refs![dst_retire, 1, 8, 8];
+    *dst_retiring_index = [self.retiring_index];
+    *dst_retiring_lpt = self.retiring_lpt.to_le_bytes();
+    *dst_retiring_reserve = self.retiring_reserve.to_le_bytes();
 
-        // Copy the token's data into the destination slice
-        dst_mint.copy_from_slice(mint.as_ref());
-        dst_treasury.copy_from_slice(treasury.as_ref());
-        *dst_reserve = reserve.to_le_bytes();
+    for i in 0..MAX_TOKEN_COUNT {
+      let last_reserve = self.last_reserves.get(i).copied().unwrap_or_default();
+      *array_mut_ref![dst_last_reserves, i * 8, 8] = last_reserve.to_le_bytes();
     }
+  }
 }
 
-  // Pack data from the data struct to [u8]
-  fn pack_into_slice(&self, dst: &mut [u8]) {
-    msg!("Pack-pack_into_slice");
-    let dst = array_mut_ref![dst, 0, 313];
-    let (
-      dst_owner,
-      dst_state,
-      dst_mint_lpt,
-      dst_vault,
-      dst_mint_s,
-      dst_treasury_s,
-      dst_reserve_s,
-      dst_mint_a,
-      dst_treasury_a,
-      dst_reserve_a,
-      dst_mint_b,
-      dst_treasury_b,
-      dst_reserve_b,
-    ) = mut_array_refs![dst, 32, 1, 32, 32, 32, 32, 8, 32, 32, 8, 32, 32, 8];
-    let &Pool {
-      ref owner,
-      state,
-      ref mint_lpt,
-      ref vault,
-      ref mint_s,
-      ref treasury_s,
-      reserve_s,
-      ref mint_a,
-      ref treasury_a,
-      reserve_a,
-      ref mint_b,
-      ref treasury_b,
-      reserve_b,
-    } = self;
-    dst_owner.copy_from_slice(owner.as_ref());
-    *dst_state = [state as u8];
-    dst_mint_lpt.copy_from_slice(mint_lpt.as_ref());
-    dst_vault.copy_from_slice(vault.as_ref());
-    dst_mint_s.copy_from_slice(mint_s.as_ref());
-    dst_treasury_s.copy_from_slice(treasury_s.as_ref());
-    *dst_reserve_s = reserve_s.to_le_bytes();
-    dst_mint_a.copy_from_slice(mint_a.as_ref());
-    dst_treasury_a.copy_from_slice(treasury_a.as_ref());
-    *dst_reserve_a = reserve_a.to_le_bytes();
-    dst_mint_b.copy_from_slice(mint_b.as_ref());
-    dst_treasury_b.copy_from_slice(treasury_b.as_ref());
-    *dst_reserve_b = reserve_b.to_le_bytes();
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_pack() {
+    let mut pool = Pool::unpack_from_slice(&[0u8; POOL_LEN]).unwrap();
+    pool.owner = Pubkey::new_unique();
+    pool.state = PoolState::Initialized;
+    pool.pause = PAUSE_SWAP;
+    pool.mints[1] = Pubkey::new_unique();
+    pool.reserves[1] = 1000;
+    pool.vaults[1] = Pubkey::new_unique();
+    pool.fee_tiers[0] = FeeTier {
+      volume: 100,
+      discount: 200,
+    };
+    pool.reference_reserves[2] = 300;
+    pool.max_deviation = 500;
+    pool.max_ask_ratio = 2000;
+    pool.price_acc = Pubkey::new_unique();
+    pool.rate_providers[3] = Pubkey::new_unique();
+    pool.rates[3] = RATE_ONE;
+    pool.rate_expiries[3] = -1;
+    pool.rate_durations[3] = 3600;
+    pool.curve = Curve::ConstantSum;
+    pool.is_earning = true;
+    pool.retiring_index = 2;
+    pool.retiring_lpt = 400;
+    pool.retiring_reserve = 1000;
+    pool.last_reserves[5] = 600;
+    let mut data = vec![0u8; POOL_LEN];
+    Pool::pack(pool.clone(), &mut data).unwrap();
+    assert_eq!(Pool::unpack(&data).unwrap(), pool);
+  }
+
+  #[test]
+  fn test_minimum_reserve() {
+    assert_eq!(Pool::minimum_reserve(0), 1);
+    assert_eq!(Pool::minimum_reserve(3), 1);
+    assert_eq!(Pool::minimum_reserve(6), 1000);
+    assert_eq!(Pool::minimum_reserve(9), 1_000_000);
+    assert_eq!(Pool::minimum_reserve(255), u64::MAX);
+  }
+
+  #[test]
+  fn test_get_rate() {
+    let mut pool = Pool::unpack_from_slice(&[0u8; POOL_LEN]).unwrap();
+    assert_eq!(pool.get_rate(0, 100), Some(RATE_ONE));
+    pool.rate_providers[0] = Pubkey::new_unique();
+    pool.rates[0] = 2 * RATE_ONE;
+    pool.rate_expiries[0] = 100;
+    assert_eq!(pool.get_rate(0, 100), Some(2 * RATE_ONE));
+    assert_eq!(pool.get_rate(0, 101), None);
+  }
+
+  #[test]
+  fn test_cache_rate() {
+    let mut pool = Pool::unpack_from_slice(&[0u8; POOL_LEN]).unwrap();
+    pool.rate_providers[1] = Pubkey::new_unique();
+    pool.rate_durations[1] = 60;
+    // The rate expires rate_duration after the provider update, not after the refresh
+    assert_eq!(pool.cache_rate(1, 2 * RATE_ONE, 1000, 1030), Some(()));
+    assert_eq!(pool.rate_expiries[1], 1060);
+    assert_eq!(pool.get_rate(1, 1060), Some(2 * RATE_ONE));
+    // A stale provider can't be cached, so the rate stays expired
+    assert_eq!(pool.cache_rate(1, 3 * RATE_ONE, 1000, 1061), None);
+    assert_eq!(pool.get_rate(1, 1061), None);
   }
 }
@@ -0,0 +1,126 @@
+use arrayref::{array_ref, array_refs};
+use solana_program::program_error::ProgramError;
+
+// Pyth-compatible price account, only the fields the pool reads
+//
+//   offset  size  field
+//   0       4     magic, 0xa1b2c3d4
+//   4       4     version
+//   8       4     account type, 3 for a price account
+//   20      4     exponent, i32
+//   208     8     aggregate price, i64
+//   216     8     aggregate confidence, u64
+//   224     4     aggregate status, 1 when trading
+//   232     8     aggregate publish slot, u64
+//
+// Nothing else is checked, the owner of the account included, so a locally created account with
+// this layout works as a mock feed. The pool owner is trusted to configure a genuine one.
+pub const PRICE_MAGIC: u32 = 0xa1b2c3d4;
+pub const PRICE_ACCOUNT_TYPE: u32 = 3;
+pub const PRICE_STATUS_TRADING: u32 = 1;
+const PRICE_LEN: usize = 240;
+
+///
+/// Price struct
+///
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Price {
+  pub expo: i32,
+  pub price: i64,
+  pub conf: u64,
+  pub status: u32,
+  pub pub_slot: u64,
+}
+
+impl Price {
+  pub fn unpack(src: &[u8]) -> Result<Self, ProgramError> {
+    if src.len() < PRICE_LEN {
+      return Err(ProgramError::InvalidAccountData);
+    }
+    let src = array_ref![src, 0, PRICE_LEN];
+    let (magic, _version, account_type, _, expo, _, price, conf, status, _, pub_slot) =
+      array_refs![src, 4, 4, 4, 8, 4, 184, 8, 8, 4, 4, 8];
+    if u32::from_le_bytes(*magic) != PRICE_MAGIC
+      || u32::from_le_bytes(*account_type) != PRICE_ACCOUNT_TYPE
+    {
+      return Err(ProgramError::InvalidAccountData);
+    }
+    Ok(Price {
+      expo: i32::from_le_bytes(*expo),
+      price: i64::from_le_bytes(*price),
+      conf: u64::from_le_bytes(*conf),
+      status: u32::from_le_bytes(*status),
+      pub_slot: u64::from_le_bytes(*pub_slot),
+    })
+  }
+
+  // Fresh, trading, positive, and with a confidence interval within max_confidence basis points
+  pub fn is_usable(&self, slot: u64, max_staleness: u64, max_confidence: u16) -> bool {
+    if self.status != PRICE_STATUS_TRADING || self.price <= 0 {
+      return false;
+    }
+    if slot.saturating_sub(self.pub_slot) > max_staleness {
+      return false;
+    }
+    (self.conf as u128) * 10000 <= (self.price as u128) * (max_confidence as u128)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use arrayref::{array_mut_ref, mut_array_refs};
+
+  fn price_data(price: i64, conf: u64, status: u32, pub_slot: u64) -> Vec<u8> {
+    let mut data = vec![0u8; PRICE_LEN];
+    let dst = array_mut_ref![data, 0, PRICE_LEN];
+    let (magic, _, account_type, _, expo, _, dst_price, dst_conf, dst_status, _, dst_pub_slot) =
+      mut_array_refs![dst, 4, 4, 4, 8, 4, 184, 8, 8, 4, 4, 8];
+    *magic = PRICE_MAGIC.to_le_bytes();
+    *account_type = PRICE_ACCOUNT_TYPE.to_le_bytes();
+    *expo = (-8i32).to_le_bytes();
+    *dst_price = price.to_le_bytes();
+    *dst_conf = conf.to_le_bytes();
+    *dst_status = status.to_le_bytes();
+    *dst_pub_slot = pub_slot.to_le_bytes();
+    data
+  }
+
+  #[test]
+  fn test_unpack() {
+    let data = price_data(100_000_000, 50_000, PRICE_STATUS_TRADING, 10);
+    assert_eq!(
+      Price::unpack(&data).unwrap(),
+      Price {
+        expo: -8,
+        price: 100_000_000,
+        conf: 50_000,
+        status: PRICE_STATUS_TRADING,
+        pub_slot: 10,
+      }
+    );
+    assert!(Price::unpack(&data[..PRICE_LEN - 1]).is_err());
+    let mut wrong_magic = data.clone();
+    wrong_magic[0] ^= 1;
+    assert!(Price::unpack(&wrong_magic).is_err());
+    let mut wrong_type = data;
+    wrong_type[8] = 2;
+    assert!(Price::unpack(&wrong_type).is_err());
+  }
+
+  #[test]
+  fn test_is_usable() {
+    let price = Price::unpack(&price_data(100_000_000, 50_000, PRICE_STATUS_TRADING, 10)).unwrap();
+    assert!(price.is_usable(15, 5, 5));
+    // Staleness
+    assert!(!price.is_usable(16, 5, 5));
+    // Confidence, 50_000 is 5 basis points of the price
+    assert!(!price.is_usable(10, 5, 4));
+    // Status
+    let halted = Price::unpack(&price_data(100_000_000, 50_000, 2, 10)).unwrap();
+    assert!(!halted.is_usable(10, 5, 5));
+    // Sign
+    let negative = Price::unpack(&price_data(-100_000_000, 0, PRICE_STATUS_TRADING, 10)).unwrap();
+    assert!(!negative.is_usable(10, 5, 5));
+  }
+}
@@ -0,0 +1,59 @@
+use crate::schema::account_type::AccountType;
+use arrayref::{array_mut_ref, array_ref, array_refs, mut_array_refs};
+use solana_program::{
+  program_error::ProgramError,
+  program_pack::{IsInitialized, Pack, Sealed},
+  pubkey::Pubkey,
+};
+
+///
+/// Rate provider struct
+///
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct RateProvider {
+  pub updater: Pubkey,
+  pub is_initialized: bool,
+  pub rate: u64, // value of one token unit in the underlying, over the oracle decimals
+  pub updated_at: i64,
+}
+
+impl Sealed for RateProvider {}
+
+impl IsInitialized for RateProvider {
+  fn is_initialized(&self) -> bool {
+    self.is_initialized
+  }
+}
+
+impl Pack for RateProvider {
+  const LEN: usize = 1 + 32 + 1 + 8 + 8;
+
+  fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
+    let src = array_ref![src, 0, 50];
+    let (account_type, updater, is_initialized, rate, updated_at) = array_refs![src, 1, 32, 1, 8, 8];
+    let is_initialized = match is_initialized {
+      [0] => false,
+      [1] => true,
+      _ => return Err(ProgramError::InvalidAccountData),
+    };
+    AccountType::check(account_type, AccountType::RateProvider, is_initialized)?;
+    Ok(RateProvider {
+      updater: Pubkey::new_from_array(*updater),
+      is_initialized,
+      rate: u64::from_le_bytes(*rate),
+      updated_at: i64::from_le_bytes(*updated_at),
+    })
+  }
+
+  fn pack_into_slice(&self, dst: &mut [u8]) {
+    let dst = array_mut_ref![dst, 0, 50];
+    let (account_type_dst, updater_dst, is_initialized_dst, rate_dst, updated_at_dst) =
+      mut_array_refs![dst, 1, 32, 1, 8, 8];
+    account_type_dst[0] = AccountType::RateProvider as u8;
+    updater_dst.copy_from_slice(self.updater.as_ref());
+    is_initialized_dst[0] = self.is_initialized as u8;
+    *rate_dst = self.rate.to_le_bytes();
+    *updated_at_dst = self.updated_at.to_le_bytes();
+  }
+}
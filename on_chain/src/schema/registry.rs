@@ -0,0 +1,101 @@
+use crate::schema::account_type::AccountType;
+use arrayref::{array_mut_ref, array_ref, array_refs, mut_array_refs};
+use solana_program::{
+  program_error::ProgramError,
+  program_pack::{IsInitialized, Pack, Sealed},
+  pubkey::Pubkey,
+};
+
+pub const MAX_ROUTERS: usize = 16;
+
+///
+/// Router registry struct
+///
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct RouterRegistry {
+  pub pool: Pubkey,
+  pub is_initialized: bool,
+  pub count: u8,
+  pub routers: [Pubkey; MAX_ROUTERS], // program ids trusted to route swaps into the pool
+}
+
+impl RouterRegistry {
+  pub fn contains(&self, router: &Pubkey) -> bool {
+    self.routers[0..self.count as usize].contains(router)
+  }
+
+  pub fn add(&mut self, router: &Pubkey) -> bool {
+    if self.count as usize >= MAX_ROUTERS || self.contains(router) {
+      return false;
+    }
+    self.routers[self.count as usize] = *router;
+    self.count += 1;
+    true
+  }
+
+  // Swap the last router into the freed slot, the order of the list doesn't matter
+  pub fn remove(&mut self, router: &Pubkey) -> bool {
+    let count = self.count as usize;
+    match self.routers[0..count].iter().position(|item| *item == *router) {
+      Some(position) => {
+        self.routers[position] = self.routers[count - 1];
+        self.routers[count - 1] = Pubkey::default();
+        self.count -= 1;
+        true
+      }
+      None => false,
+    }
+  }
+}
+
+impl Sealed for RouterRegistry {}
+
+impl IsInitialized for RouterRegistry {
+  fn is_initialized(&self) -> bool {
+    self.is_initialized
+  }
+}
+
+impl Pack for RouterRegistry {
+  const LEN: usize = 1 + 32 + 1 + 1 + 32 * MAX_ROUTERS;
+
+  fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
+    let src = array_ref![src, 0, 547];
+    let (account_type, pool, is_initialized, count, routers_flat) =
+      array_refs![src, 1, 32, 1, 1, 32 * MAX_ROUTERS];
+    let is_initialized = match is_initialized {
+      [0] => false,
+      [1] => true,
+      _ => return Err(ProgramError::InvalidAccountData),
+    };
+    AccountType::check(account_type, AccountType::RouterRegistry, is_initialized)?;
+    if count[0] as usize > MAX_ROUTERS {
+      return Err(ProgramError::InvalidAccountData);
+    }
+    let mut routers = [Pubkey::default(); MAX_ROUTERS];
+    for (i, router) in routers.iter_mut().enumerate() {
+      *router = Pubkey::new_from_array(*array_ref![routers_flat, i * 32, 32]);
+    }
+    Ok(RouterRegistry {
+      pool: Pubkey::new_from_array(*pool),
+      is_initialized,
+      count: count[0],
+      routers,
+    })
+  }
+
+  fn pack_into_slice(&self, dst: &mut [u8]) {
+    let dst = array_mut_ref![dst, 0, 547];
+    let (account_type_dst, pool_dst, is_initialized_dst, count_dst, routers_flat_dst) =
+      mut_array_refs![dst, 1, 32, 1, 1, 32 * MAX_ROUTERS];
+    account_type_dst[0] = AccountType::RouterRegistry as u8;
+    pool_dst.copy_from_slice(self.pool.as_ref());
+    is_initialized_dst[0] = self.is_initialized as u8;
+    count_dst[0] = self.count;
+    for (i, router) in self.routers.iter().enumerate() {
+      let router_dst = array_mut_ref![routers_flat_dst, i * 32, 32];
+      router_dst.copy_from_slice(router.as_ref());
+    }
+  }
+}
@@ -0,0 +1,102 @@
+use crate::schema::account_type::AccountType;
+use arrayref::{array_mut_ref, array_ref, array_refs, mut_array_refs};
+use solana_program::{
+  program_error::ProgramError,
+  program_pack::{IsInitialized, Pack, Sealed},
+  pubkey::Pubkey,
+};
+
+pub const VOLUME_DAYS: usize = 30;
+pub const SECONDS_PER_DAY: i64 = 86400;
+
+///
+/// Trader volume struct
+///
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct TraderVolume {
+  pub pool: Pubkey,
+  pub trader: Pubkey,
+  pub is_initialized: bool,
+  pub last_day: u64,
+  pub daily: [u64; VOLUME_DAYS], // ring of daily notional in token 0, indexed by day
+}
+
+impl TraderVolume {
+  // Clear the days that went by since the last record
+  pub fn roll(&mut self, day: u64) {
+    if day <= self.last_day {
+      return;
+    }
+    let elapsed = (day - self.last_day).min(VOLUME_DAYS as u64);
+    for i in 1..=elapsed {
+      self.daily[((self.last_day + i) % VOLUME_DAYS as u64) as usize] = 0;
+    }
+    self.last_day = day;
+  }
+
+  pub fn record(&mut self, day: u64, volume: u64) {
+    self.roll(day);
+    let bucket = &mut self.daily[(day % VOLUME_DAYS as u64) as usize];
+    *bucket = bucket.saturating_add(volume);
+  }
+
+  // Rolling volume of the last VOLUME_DAYS days, today included
+  pub fn total(&self, day: u64) -> u64 {
+    let mut rolled = *self;
+    rolled.roll(day);
+    rolled
+      .daily
+      .iter()
+      .fold(0u64, |total, &volume| total.saturating_add(volume))
+  }
+}
+
+impl Sealed for TraderVolume {}
+
+impl IsInitialized for TraderVolume {
+  fn is_initialized(&self) -> bool {
+    self.is_initialized
+  }
+}
+
+impl Pack for TraderVolume {
+  const LEN: usize = 1 + 32 + 32 + 1 + 8 + 8 * VOLUME_DAYS;
+
+  fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
+    let src = array_ref![src, 0, 314];
+    let (account_type, pool, trader, is_initialized, last_day, daily_flat) =
+      array_refs![src, 1, 32, 32, 1, 8, 8 * VOLUME_DAYS];
+    let is_initialized = match is_initialized {
+      [0] => false,
+      [1] => true,
+      _ => return Err(ProgramError::InvalidAccountData),
+    };
+    AccountType::check(account_type, AccountType::TraderVolume, is_initialized)?;
+    let mut daily = [0u64; VOLUME_DAYS];
+    for (i, volume) in daily.iter_mut().enumerate() {
+      *volume = u64::from_le_bytes(*array_ref![daily_flat, i * 8, 8]);
+    }
+    Ok(TraderVolume {
+      pool: Pubkey::new_from_array(*pool),
+      trader: Pubkey::new_from_array(*trader),
+      is_initialized,
+      last_day: u64::from_le_bytes(*last_day),
+      daily,
+    })
+  }
+
+  fn pack_into_slice(&self, dst: &mut [u8]) {
+    let dst = array_mut_ref![dst, 0, 314];
+    let (account_type_dst, pool_dst, trader_dst, is_initialized_dst, last_day_dst, daily_flat_dst) =
+      mut_array_refs![dst, 1, 32, 32, 1, 8, 8 * VOLUME_DAYS];
+    account_type_dst[0] = AccountType::TraderVolume as u8;
+    pool_dst.copy_from_slice(self.pool.as_ref());
+    trader_dst.copy_from_slice(self.trader.as_ref());
+    is_initialized_dst[0] = self.is_initialized as u8;
+    *last_day_dst = self.last_day.to_le_bytes();
+    for (i, volume) in self.daily.iter().enumerate() {
+      *array_mut_ref![daily_flat_dst, i * 8, 8] = volume.to_le_bytes();
+    }
+  }
+}